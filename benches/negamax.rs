@@ -6,11 +6,12 @@ mod connect4;
 
 use bencher::Bencher;
 use minimax::*;
+use std::sync::Once;
 
 fn bench_negamax(b: &mut Bencher) {
     let board = connect4::Board::default();
     b.iter(|| {
-        let mut s = Negamax::new(connect4::BasicEvaluator::default(), 5);
+        let mut s = Negamax::new(connect4::BasicEvaluator, 5);
         let m = s.choose_move(&board);
         assert!(m.is_some());
     });
@@ -20,7 +21,20 @@ fn bench_iterative(b: &mut Bencher) {
     let board = connect4::Board::default();
     b.iter(|| {
         let mut s = IterativeSearch::new(
-            connect4::BasicEvaluator::default(),
+            connect4::BasicEvaluator,
+            IterativeOptions::new().with_table_byte_size(32_000),
+        );
+        s.set_max_depth(5);
+        let m = s.choose_move(&board);
+        assert!(m.is_some());
+    });
+}
+
+fn bench_mtdf(b: &mut Bencher) {
+    let board = connect4::Board::default();
+    b.iter(|| {
+        let mut s = MtdfSearch::new(
+            connect4::BasicEvaluator,
             IterativeOptions::new().with_table_byte_size(32_000),
         );
         s.set_max_depth(5);
@@ -32,8 +46,8 @@ fn bench_iterative(b: &mut Bencher) {
 fn bench_parallel(b: &mut Bencher) {
     let board = connect4::Board::default();
     b.iter(|| {
-        let mut s = ParallelYbw::new(
-            connect4::BasicEvaluator::default(),
+        let mut s = ParallelYbw::new_packed(
+            connect4::BasicEvaluator,
             YbwOptions::new().with_table_byte_size(32_000),
         );
         s.set_max_depth(5);
@@ -42,5 +56,53 @@ fn bench_parallel(b: &mut Bencher) {
     });
 }
 
-benchmark_group!(benches, bench_negamax, bench_iterative, bench_parallel);
+// Compare transposition table replacement strategies with a table too small
+// for the search, reporting the nodes needed to reach the depth and the rate
+// of table cutoffs.
+fn bench_replacement(b: &mut Bencher, strategy: Replacement, report: &Once) {
+    let board = connect4::Board::default();
+    let mut stats = SearchStats::default();
+    b.iter(|| {
+        let mut s = IterativeSearch::new(
+            connect4::BasicEvaluator,
+            IterativeOptions::new()
+                .with_table_byte_size(16_000)
+                .with_replacement_strategy(strategy),
+        );
+        s.set_max_depth(8);
+        let m = s.choose_move(&board);
+        assert!(m.is_some());
+        stats = s.stats();
+    });
+    // The benchmark is run several times to calibrate.
+    report.call_once(|| {
+        eprintln!(
+            "{:?}: {} nodes to depth {}, {:.1}% table hits",
+            strategy,
+            stats.total_nodes(),
+            stats.depth,
+            100.0 * stats.table_hits as f64 / stats.total_nodes() as f64
+        )
+    });
+}
+
+fn bench_two_tier(b: &mut Bencher) {
+    static REPORT: Once = Once::new();
+    bench_replacement(b, Replacement::TwoTier, &REPORT);
+}
+
+fn bench_bucket(b: &mut Bencher) {
+    static REPORT: Once = Once::new();
+    bench_replacement(b, Replacement::Bucket(4), &REPORT);
+}
+
+benchmark_group!(
+    benches,
+    bench_negamax,
+    bench_iterative,
+    bench_mtdf,
+    bench_parallel,
+    bench_two_tier,
+    bench_bucket
+);
 benchmark_main!(benches);
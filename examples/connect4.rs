@@ -6,7 +6,7 @@ extern crate minimax;
 use std::default::Default;
 use std::fmt::{Display, Formatter, Result};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Board {
     // Some bitboard ideas from http://blog.gamesolver.org/solving-connect-four/06-bitboard/
     /* bit order example:
@@ -46,12 +46,6 @@ impl Board {
     }
 }
 
-impl Default for Board {
-    fn default() -> Board {
-        Board { all_pieces: 0, pieces_to_move: 0, num_moves: 0, hash: 0 }
-    }
-}
-
 impl minimax::Zobrist for Board {
     fn zobrist_hash(&self) -> u64 {
         self.hash
@@ -78,7 +72,7 @@ impl Display for Board {
                     }
                 )?;
             }
-            writeln!(f, "")?;
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -118,6 +112,37 @@ impl minimax::Move for Place {
     }
 }
 
+impl minimax::PackableMove for Place {
+    fn pack(&self) -> u16 {
+        self.col as u16
+    }
+
+    fn unpack(packed: u16) -> Option<Self> {
+        if (packed as u32) < NUM_COLS {
+            Some(Place { col: packed as u8 })
+        } else {
+            None
+        }
+    }
+}
+
+impl minimax::SerializableMove for Place {
+    const ENCODING: &'static str = "connect4 column";
+    const ENCODED_SIZE: usize = 1;
+
+    fn encode(&self, bytes: &mut [u8]) {
+        bytes[0] = self.col;
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if (bytes[0] as u32) < NUM_COLS {
+            Some(Place { col: bytes[0] })
+        } else {
+            None
+        }
+    }
+}
+
 pub struct Game;
 
 impl minimax::Game for Game {
@@ -142,7 +167,7 @@ impl minimax::Game for Game {
         // row.
         let matches = |shift| -> bool {
             let pairs = pieces & (pieces >> shift);
-            pairs & (pairs >> 2 * shift) != 0
+            pairs & (pairs >> (2 * shift)) != 0
         };
 
         if matches(1) || matches(HEIGHT) || matches(HEIGHT + 1) || matches(HEIGHT - 1) {
@@ -156,6 +181,14 @@ impl minimax::Game for Game {
             None
         }
     }
+
+    fn move_index(m: &Place) -> usize {
+        m.col as usize
+    }
+
+    fn num_move_indices() -> usize {
+        NUM_COLS as usize
+    }
 }
 
 pub struct DumbEvaluator;
@@ -181,7 +214,7 @@ impl Board {
         let matches = |shift| -> u64 {
             let pairs = pieces & (pieces >> shift); // Pairs of this color.
             let singles = (pieces >> shift) & !all | (pieces << shift) & !all; // One of this color and one empty.
-            (pairs >> shift * 2) & singles | (pairs << shift * 2) & singles
+            (pairs >> (shift * 2)) & singles | (pairs << (shift * 2)) & singles
         };
 
         // Vertical
@@ -195,15 +228,9 @@ impl Board {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct BasicEvaluator;
 
-impl Default for BasicEvaluator {
-    fn default() -> Self {
-        Self {}
-    }
-}
-
 impl minimax::Evaluator for BasicEvaluator {
     type G = Game;
     fn evaluate(&self, b: &Board) -> minimax::Evaluation {
@@ -260,43 +287,48 @@ fn main() {
         return;
     }
 
-    let mut dumb = IterativeSearch::new(
-        BasicEvaluator::default(),
-        IterativeOptions::new().with_double_step_increment(),
-    );
+    let mut dumb =
+        IterativeSearch::new(BasicEvaluator, IterativeOptions::new().with_double_step_increment());
     dumb.set_max_depth(8);
 
     let opts = IterativeOptions::new()
         .with_table_byte_size(64_000_000)
         .with_double_step_increment()
         .with_aspiration_window(5);
-    let mut iterative = IterativeSearch::new(BasicEvaluator::default(), opts);
+    let mut iterative = IterativeSearch::new_packed(BasicEvaluator, opts);
     iterative.set_max_depth(12);
-    let mut parallelybw = ParallelYbw::new(
-        BasicEvaluator::default(),
+    let mut parallelybw = ParallelYbw::new_packed(
+        BasicEvaluator,
         YbwOptions::new().with_table_byte_size(64_000_000).with_double_step_increment(),
     );
     parallelybw.set_max_depth(12);
-    let mut lazysmp = LazySmp::new(
-        BasicEvaluator::default(),
+    let mut lazysmp = LazySmp::new_packed(
+        BasicEvaluator,
         LazySmpOptions::new().with_table_byte_size(64_000_000).with_double_step_increment(),
     );
     lazysmp.set_max_depth(12);
+    let mut mtdf = MtdfSearch::new_packed(
+        BasicEvaluator,
+        IterativeOptions::new().with_table_byte_size(64_000_000).with_double_step_increment(),
+    );
+    mtdf.set_max_depth(12);
 
-    let mut strategies: [&mut dyn Strategy<self::Game>; 4] =
-        [&mut dumb, &mut iterative, &mut parallelybw, &mut lazysmp];
+    let mut strategies: [&mut dyn Strategy<self::Game>; 5] =
+        [&mut dumb, &mut iterative, &mut parallelybw, &mut lazysmp, &mut mtdf];
 
     if std::env::args().any(|arg| arg == "parallel") {
         strategies.swap(1, 2);
     } else if std::env::args().any(|arg| arg == "lazysmp") {
         strategies.swap(1, 3);
+    } else if std::env::args().any(|arg| arg == "mtdf") {
+        strategies.swap(1, 4);
     }
 
     let mut s = 0;
     while self::Game::get_winner(&b).is_none() {
         println!("{}", b);
-        let ref mut strategy = strategies[s];
-        match strategy.choose_move(&mut b) {
+        let strategy = &mut strategies[s];
+        match strategy.choose_move(&b) {
             Some(m) => {
                 let color = if b.reds_move() { "Red" } else { "Yellow" };
                 println!("{} piece in column {}", color, m.col + 1);
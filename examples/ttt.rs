@@ -10,9 +10,10 @@ extern crate minimax;
 use std::default::Default;
 use std::fmt::{Display, Formatter, Result};
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Square {
+    #[default]
     Empty,
     X,
     O,
@@ -28,12 +29,6 @@ impl Square {
     }
 }
 
-impl Default for Square {
-    fn default() -> Square {
-        Square::Empty
-    }
-}
-
 impl Display for Square {
     fn fmt(&self, f: &mut Formatter) -> Result {
         write!(
@@ -176,14 +171,9 @@ impl minimax::Move for Place {
     }
 }
 
+#[derive(Default)]
 pub struct Evaluator;
 
-impl Default for Evaluator {
-    fn default() -> Self {
-        Self {}
-    }
-}
-
 impl minimax::Evaluator for Evaluator {
     type G = Game;
     // adapted from http://www.cs.olemiss.edu/~dwilkins/CSCI531/tic.c
@@ -193,10 +183,10 @@ impl minimax::Evaluator for Evaluator {
         // 3rd: check for doubles
         for i in 0..3 {
             let line = i * 3;
-            if b.squares[line + 0] == b.squares[line + 1] {
-                if b.squares[line + 0] == Square::X {
+            if b.squares[line] == b.squares[line + 1] {
+                if b.squares[line] == Square::X {
                     score += 5;
-                } else if b.squares[line + 0] == Square::O {
+                } else if b.squares[line] == Square::O {
                     score -= 5;
                 }
             }
@@ -204,7 +194,7 @@ impl minimax::Evaluator for Evaluator {
                 if b.squares[line + 1] == Square::X {
                     score += 5;
                 } else if b.squares[line + 1] == Square::O {
-                    score += 5;
+                    score -= 5;
                 }
             }
             if b.squares[i] == b.squares[3 + i] {
@@ -242,13 +232,12 @@ fn main() {
     use minimax::{Game, Move, Strategy};
 
     let mut b = Board::default();
-    let mut strategies =
-        vec![Negamax::new(Evaluator::default(), 10), Negamax::new(Evaluator::default(), 10)];
+    let mut strategies = [Negamax::new(Evaluator, 10), Negamax::new(Evaluator, 10)];
     let mut s = 0;
     while self::Game::get_winner(&b).is_none() {
         println!("{}", b);
-        let ref mut strategy = strategies[s];
-        match strategy.choose_move(&mut b) {
+        let strategy = &mut strategies[s];
+        match strategy.choose_move(&b) {
             Some(m) => m.apply(&mut b),
             None => break,
         }
@@ -1,5 +1,7 @@
 //! The common structures and traits.
 
+use std::fmt;
+
 /// An assessment of a game state from the perspective of the player whose turn it is to play.
 /// Higher values mean a more favorable state.
 /// A draw is defined as a score of zero.
@@ -14,6 +16,70 @@ pub const BEST_EVAL: Evaluation = i32::MAX;
 /// An absolutely disastrous outcome, e.g. a loss.
 pub const WORST_EVAL: Evaluation = -BEST_EVAL;
 
+/// Evaluations within this distance of `BEST_EVAL` or `WORST_EVAL` are
+/// forced wins or losses, counted in plies from the root of the search:
+/// `BEST_EVAL - 3` is a win in 3 plies. Evaluators shouldn't return values in
+/// this range.
+pub const WIN_DISTANCE: Evaluation = 1000;
+
+/// The result of a search from the perspective of the player to move at the
+/// root.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Score {
+    /// The player to move can force a win in this many plies.
+    WinIn(u32),
+    /// The player to move loses in this many plies against best play.
+    LossIn(u32),
+    /// No forced result was found, with this evaluation.
+    Eval(Evaluation),
+}
+
+impl From<Evaluation> for Score {
+    fn from(value: Evaluation) -> Score {
+        if value > BEST_EVAL - WIN_DISTANCE {
+            Score::WinIn((BEST_EVAL - value) as u32)
+        } else if value < WORST_EVAL + WIN_DISTANCE {
+            Score::LossIn((value - WORST_EVAL) as u32)
+        } else {
+            Score::Eval(value)
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Score::WinIn(plies) => write!(f, "win in {} plies", plies),
+            Score::LossIn(plies) => write!(f, "loss in {} plies", plies),
+            Score::Eval(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// The moves of the root that a search may choose from. Other moves are
+/// still searched deeper in the tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RootMoves<M> {
+    /// Every move from `generate_moves`.
+    #[default]
+    All,
+    /// Only these moves, also known as "searchmoves".
+    Only(Vec<M>),
+    /// Every move except these.
+    Except(Vec<M>),
+}
+
+impl<M: PartialEq> RootMoves<M> {
+    /// Whether the search may choose this move.
+    pub fn allows(&self, m: &M) -> bool {
+        match self {
+            RootMoves::All => true,
+            RootMoves::Only(moves) => moves.contains(m),
+            RootMoves::Except(moves) => !moves.contains(m),
+        }
+    }
+}
+
 /// Evaluates a game's positions.
 pub trait Evaluator {
     /// The type of game that can be evaluated.
@@ -59,6 +125,16 @@ impl Winner {
             Winner::Draw => 0,
         }
     }
+
+    /// Evaluation of an end state reached `ply` moves from the root of a
+    /// search, so that faster wins and slower losses are preferred.
+    pub fn evaluate_at_ply(&self, ply: usize) -> Evaluation {
+        match *self {
+            Winner::PlayerJustMoved => WORST_EVAL + ply as Evaluation,
+            Winner::PlayerToMove => BEST_EVAL - ply as Evaluation,
+            Winner::Draw => 0,
+        }
+    }
 }
 
 /// An optional trait for game state types to support hashing.
@@ -72,6 +148,39 @@ pub trait Zobrist {
     fn zobrist_hash(&self) -> u64;
 }
 
+/// An optional trait for move types to support saving and loading
+/// transposition tables.
+pub trait SerializableMove: Sized {
+    /// Identifies the encoding. Saved tables are only loaded by moves with
+    /// the same encoding, so change it whenever the encoding changes.
+    const ENCODING: &'static str;
+    /// The number of bytes in each encoded move.
+    const ENCODED_SIZE: usize;
+
+    /// Write the move into `ENCODED_SIZE` bytes.
+    fn encode(&self, bytes: &mut [u8]);
+
+    /// Read a move written by `encode`, or None if the bytes aren't a move.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+/// An optional trait for move types that fit in 16 bits, which lets
+/// transposition tables hold more positions in the same memory for games
+/// with large move types, and lets the threads of `LazySmp` and
+/// `ParallelYbw` share their table without locks. Strategies only pack
+/// their tables when created with `new_packed`. Other tables of `LazySmp`
+/// and `ParallelYbw` lock each entry with a mutex.
+///
+/// Packed tables also store evaluations in 16 bits, so evaluations larger
+/// than `i16::MAX - WIN_DISTANCE` in magnitude are only kept as bounds.
+pub trait PackableMove: Sized {
+    /// Pack the move into 16 bits.
+    fn pack(&self) -> u16;
+
+    /// Unpack a move packed by `pack`, or None if the bits aren't a move.
+    fn unpack(packed: u16) -> Option<Self>;
+}
+
 /// Defines the rules for a two-player, perfect-knowledge game.
 ///
 /// A game ties together types for the state and moves, generates the possible
@@ -99,12 +208,70 @@ pub trait Game: Sized {
         // immediately.
     }
 
+    /// Optional interface to support delta pruning in quiescence search.
+    ///
+    /// Returns an upper bound on how much the noisy move can improve the
+    /// evaluation for the player making it, such as the value of the
+    /// captured piece plus a safety margin. Noisy moves that cannot raise
+    /// the evaluation enough to matter are skipped. When unimplemented, or
+    /// for moves that return `None` (e.g. a move that wins the game), the
+    /// move is always searched.
+    fn delta_pruning_margin(_state: &Self::S, _m: &Self::M) -> Option<Evaluation> {
+        None
+    }
+
+    /// Optional interface to support null-move pruning.
+    ///
+    /// Returns a move that passes the turn to the opponent without otherwise
+    /// changing the state, which strategies use to quickly prove that a
+    /// position is too good for the opponent to allow. The state's
+    /// `Zobrist` hash must change to reflect the new player to move.
+    ///
+    /// Should return `None` where passing would be unsafe, such as in
+    /// positions where every move makes things worse (zugzwang). When
+    /// unimplemented, null-move pruning is disabled.
+    fn null_move(_state: &Self::S) -> Option<Self::M> {
+        None
+    }
+
+    /// Optional interface to support move ordering heuristics that learn
+    /// which moves tend to be good across positions, such as the history
+    /// heuristic.
+    ///
+    /// Returns an index for the move that is less than `num_move_indices()`.
+    /// Similar moves in different positions should share an index, e.g. a
+    /// chess move could be indexed by its origin and destination squares.
+    fn move_index(_m: &Self::M) -> usize {
+        0
+    }
+
+    /// The number of distinct indices returned by `move_index`. When
+    /// unimplemented, the history and countermove heuristics are disabled.
+    fn num_move_indices() -> usize {
+        0
+    }
+
     /// Returns `Some(PlayerJustMoved)` or `Some(PlayerToMove)` if there's a winner,
     /// `Some(Draw)` if the state is terminal without a winner, and `None` if
     /// the state is non-terminal.
     fn get_winner(state: &Self::S) -> Option<Winner>;
 }
 
+/// An optional trait for games with random events, such as dice rolls or card
+/// draws.
+///
+/// A state where the next event is random instead of chosen by a player is a
+/// chance node. Each outcome at a chance node is represented by a move, which
+/// is applied and undone like any other move. A chance outcome does not
+/// change whose turn it is: evaluations of a chance node are from the
+/// perspective of the player who moves after the random event.
+pub trait ChanceGame: Game {
+    /// Generate the random outcomes at the given state along with their
+    /// probabilities, which should sum to one. Leaves `outcomes` empty if
+    /// the state is not a chance node.
+    fn generate_outcomes(state: &Self::S, outcomes: &mut Vec<(Self::M, f64)>);
+}
+
 /// Defines a method of choosing a move for the current player.
 pub trait Strategy<G: Game> {
     fn choose_move(&mut self, state: &G::S) -> Option<G::M>;
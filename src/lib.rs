@@ -5,10 +5,23 @@ pub mod strategies;
 pub mod util;
 
 pub use interface::{
-    Evaluation, Evaluator, Game, Move, Strategy, Winner, Zobrist, BEST_EVAL, WORST_EVAL,
+    ChanceGame, Evaluation, Evaluator, Game, Move, PackableMove, RootMoves, Score,
+    SerializableMove, Strategy, Winner, Zobrist, BEST_EVAL, WIN_DISTANCE, WORST_EVAL,
+};
+pub use strategies::book::{BookBuilder, BookStrategy, OpeningBook};
+pub use strategies::expectiminimax::{Expectiminimax, ExpectiminimaxOptions, StarPruning};
+pub use strategies::iterative::{
+    IterativeOptions, IterativeSearch, LateMoveReductions, RepetitionRule, Replacement,
 };
-pub use strategies::iterative::{IterativeOptions, IterativeSearch, Replacement};
 pub use strategies::lazy_smp::{LazySmp, LazySmpOptions};
+pub use strategies::mcts::{MctsOptions, MonteCarloTreeSearch};
+pub use strategies::mtdf::MtdfSearch;
 pub use strategies::negamax::Negamax;
+pub use strategies::pns::{Outcome, PnsOptions, Proof, ProofNumberSearch};
+pub use strategies::ponder::{Ponderable, PonderingStrategy};
+pub use strategies::progress::SearchProgress;
+pub use strategies::skill::{SkillLevel, SkillOptions, MAX_SKILL_LEVEL};
+pub use strategies::stats::SearchStats;
+pub use strategies::time::{SearchLimits, TimeControl};
 pub use strategies::ybw::{ParallelYbw, YbwOptions};
 pub use util::perft;
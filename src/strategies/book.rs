@@ -0,0 +1,249 @@
+//! An opening book, and a strategy that plays from it.
+//!
+//! The book maps the Zobrist hash of each position to a list of weighted
+//! moves. Moves are stored by their index in the order that
+//! `Game::generate_moves` produces them, so that books can be saved to disk
+//! without knowing how to serialize moves. This requires move generation to
+//! be deterministic.
+//!
+//! Books are populated with a `BookBuilder`, either by analyzing each
+//! position with a search strategy or by replaying recorded games.
+
+use super::super::interface::*;
+use super::util::{read_u16, read_u32, read_u64, seeded_rng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::marker::PhantomData;
+
+// Identifies the on-disk format.
+const MAGIC: &[u8; 4] = b"MMBK";
+const VERSION: u8 = 1;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct BookMove {
+    // Index into the generated moves of the position.
+    index: usize,
+    weight: u32,
+}
+
+/// A collection of weighted moves for known positions.
+pub struct OpeningBook<G: Game> {
+    positions: HashMap<u64, Vec<BookMove>>,
+    game_type: PhantomData<G>,
+}
+
+impl<G: Game> OpeningBook<G> {
+    pub fn new() -> Self {
+        Self { positions: HashMap::new(), game_type: PhantomData }
+    }
+
+    /// The number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Write the book in a compact binary format.
+    ///
+    /// The format is a 4-byte magic number and a version byte, followed by
+    /// the number of positions. Each position is its hash and number of
+    /// moves, followed by the index and weight of each move. All integers
+    /// are little-endian. Fails with `InvalidInput` if a position has more
+    /// moves than fit in 16 bits.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<()> {
+        let too_many_moves = |_| Error::new(ErrorKind::InvalidInput, "too many moves to write");
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        w.write_all(&(self.positions.len() as u64).to_le_bytes())?;
+        // Sort the positions so that the same book is always written the
+        // same way.
+        let mut hashes: Vec<u64> = self.positions.keys().copied().collect();
+        hashes.sort_unstable();
+        for hash in hashes {
+            let moves = &self.positions[&hash];
+            w.write_all(&hash.to_le_bytes())?;
+            w.write_all(&u16::try_from(moves.len()).map_err(too_many_moves)?.to_le_bytes())?;
+            for m in moves.iter() {
+                w.write_all(&u16::try_from(m.index).map_err(too_many_moves)?.to_le_bytes())?;
+                w.write_all(&m.weight.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a book written by `write_to`.
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+        let mut magic = [0; 5];
+        r.read_exact(&mut magic)?;
+        if &magic[..4] != MAGIC || magic[4] != VERSION {
+            return Err(Error::new(ErrorKind::InvalidData, "not an opening book"));
+        }
+        let mut book = Self::new();
+        for _ in 0..read_u64(r)? {
+            let hash = read_u64(r)?;
+            let mut moves = Vec::new();
+            for _ in 0..read_u16(r)? {
+                let index = read_u16(r)? as usize;
+                let weight = read_u32(r)?;
+                moves.push(BookMove { index, weight });
+            }
+            book.positions.insert(hash, moves);
+        }
+        Ok(book)
+    }
+}
+
+impl<G: Game> Default for OpeningBook<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G: Game> OpeningBook<G>
+where
+    G::S: Zobrist,
+    G::M: Copy + Eq,
+{
+    /// Add weight to a move from the position. The move must be legal.
+    pub fn add_move(&mut self, state: &G::S, m: G::M, weight: u32) {
+        let mut moves = Vec::new();
+        G::generate_moves(state, &mut moves);
+        let index = moves.iter().position(|&legal| legal == m).expect("move is not legal");
+        let book_moves = self.positions.entry(state.zobrist_hash()).or_default();
+        match book_moves.iter_mut().find(|book_move| book_move.index == index) {
+            Some(book_move) => book_move.weight = book_move.weight.saturating_add(weight),
+            None => book_moves.push(BookMove { index, weight }),
+        }
+    }
+
+    /// The moves and their weights in the book for the position.
+    pub fn probe(&self, state: &G::S) -> Vec<(G::M, u32)> {
+        let book_moves = match self.positions.get(&state.zobrist_hash()) {
+            Some(book_moves) => book_moves,
+            None => return Vec::new(),
+        };
+        let mut moves = Vec::new();
+        G::generate_moves(state, &mut moves);
+        // Skip moves that don't exist, in case of a hash collision.
+        book_moves
+            .iter()
+            .filter_map(|book_move| Some((*moves.get(book_move.index)?, book_move.weight)))
+            .collect()
+    }
+
+    /// Randomly choose a book move for the position, in proportion to the
+    /// weights. Returns None if the position is out of book.
+    pub fn choose_move<R: rand::Rng>(&self, state: &G::S, rng: &mut R) -> Option<G::M> {
+        let moves = self.probe(state);
+        // Sum the weights in 64 bits, so that saturated weights don't overflow.
+        moves.choose_weighted(rng, |&(_, weight)| weight as u64).ok().map(|&(m, _)| m)
+    }
+}
+
+/// Populates an opening book.
+pub struct BookBuilder<G: Game> {
+    book: OpeningBook<G>,
+    max_plies: usize,
+}
+
+impl<G: Game> BookBuilder<G>
+where
+    G::S: Zobrist,
+    G::M: Copy + Eq,
+{
+    /// Build a book that covers positions up to `max_plies` moves after the
+    /// starting position.
+    pub fn new(max_plies: usize) -> Self {
+        Self { book: OpeningBook::new(), max_plies }
+    }
+
+    /// Add a recorded game, given by its moves from the starting position.
+    /// Each move played within the first `max_plies` gains a weight of one.
+    pub fn add_game(&mut self, start: &G::S, moves: &[G::M])
+    where
+        G::S: Clone,
+    {
+        let mut state = start.clone();
+        for &m in moves.iter().take(self.max_plies) {
+            self.book.add_move(&state, m, 1);
+            m.apply(&mut state);
+        }
+    }
+
+    /// Analyze every position within `max_plies` of the start with the
+    /// strategy, and add the move it chooses with a weight of one. This
+    /// covers every reply for both players, so the number of positions
+    /// grows exponentially with `max_plies`.
+    pub fn add_analysis<S: Strategy<G>>(&mut self, start: &G::S, strategy: &mut S)
+    where
+        G::S: Clone,
+    {
+        let mut state = start.clone();
+        self.analyze(&mut state, strategy, self.max_plies);
+    }
+
+    fn analyze<S: Strategy<G>>(&mut self, state: &mut G::S, strategy: &mut S, plies: usize) {
+        if plies == 0 || G::get_winner(state).is_some() {
+            return;
+        }
+        if let Some(m) = strategy.choose_move(state) {
+            self.book.add_move(state, m, 1);
+        }
+        let mut moves = Vec::new();
+        G::generate_moves(state, &mut moves);
+        for m in moves {
+            m.apply(state);
+            self.analyze(state, strategy, plies - 1);
+            m.undo(state);
+        }
+    }
+
+    pub fn build(self) -> OpeningBook<G> {
+        self.book
+    }
+}
+
+/// A strategy that plays from an opening book, and falls back to another
+/// strategy when out of book.
+pub struct BookStrategy<G: Game, S> {
+    book: OpeningBook<G>,
+    inner: S,
+    rng: StdRng,
+}
+
+impl<G: Game, S: Strategy<G>> BookStrategy<G, S> {
+    pub fn new(book: OpeningBook<G>, inner: S) -> Self {
+        Self { book, inner, rng: seeded_rng(None, 0) }
+    }
+
+    /// Seed the choice among book moves, so that the same seed plays the
+    /// same moves. The inner strategy is seeded separately.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = seeded_rng(Some(seed), 0);
+    }
+
+    pub fn book(&self) -> &OpeningBook<G> {
+        &self.book
+    }
+
+    /// The strategy used when out of book, e.g. to change its settings.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<G: Game, S: Strategy<G>> Strategy<G> for BookStrategy<G, S>
+where
+    G::S: Zobrist,
+    G::M: Copy + Eq,
+{
+    fn choose_move(&mut self, state: &G::S) -> Option<G::M> {
+        self.book.choose_move(state, &mut self.rng).or_else(|| self.inner.choose_move(state))
+    }
+}
@@ -0,0 +1,350 @@
+//! An implementation of Expectiminimax, for games with chance nodes.
+//!
+//! Player nodes are searched with negamax and alpha-beta pruning, while the
+//! value of a chance node is the probability-weighted average of its
+//! outcomes. Alpha-beta style cutoffs at chance nodes are possible with the
+//! Star1 and Star2 algorithms, which use bounds on the evaluation function to
+//! prove that the remaining outcomes cannot bring the average back within
+//! the search window.
+//!
+//! Wins and losses are scored by their distance from the root, as in the
+//! other strategies, while the path to them has no chance nodes. Behind a
+//! chance node they only count as the bound on the evaluation, since the
+//! outcomes make them likely rather than forced.
+
+use super::super::interface::*;
+use super::super::util::*;
+
+use std::cmp::{max, min};
+
+/// Which pruning algorithm to use at chance nodes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StarPruning {
+    /// Search every outcome with a full window.
+    None,
+    /// Narrow the window of each outcome based on the outcomes searched so
+    /// far and the evaluation bounds.
+    Star1,
+    /// Like Star1, but first probe one move of each outcome to find lower
+    /// bounds that can cause an early cutoff or narrow later windows.
+    Star2,
+}
+
+/// Options to use for the Expectiminimax engine.
+#[derive(Clone, Copy)]
+pub struct ExpectiminimaxOptions {
+    pruning: StarPruning,
+    eval_bound: Evaluation,
+}
+
+impl ExpectiminimaxOptions {
+    pub fn new() -> Self {
+        ExpectiminimaxOptions { pruning: StarPruning::Star2, eval_bound: BEST_EVAL - WIN_DISTANCE }
+    }
+}
+
+impl Default for ExpectiminimaxOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExpectiminimaxOptions {
+    /// Which pruning algorithm to use at chance nodes.
+    pub fn with_pruning(mut self, pruning: StarPruning) -> Self {
+        self.pruning = pruning;
+        self
+    }
+
+    /// The largest absolute value that the `Evaluator` can return. Star
+    /// pruning is much more effective with a tight bound. Wins and losses
+    /// behind a chance node are scored as plus and minus the bound, and
+    /// evaluations outside of it are clamped. Bounds within `WIN_DISTANCE`
+    /// of `BEST_EVAL` are lowered out of that range.
+    pub fn with_eval_bound(mut self, bound: Evaluation) -> Self {
+        self.eval_bound = min(bound, BEST_EVAL - WIN_DISTANCE);
+        self
+    }
+}
+
+pub struct Expectiminimax<E: Evaluator> {
+    max_depth: usize,
+    move_pool: MovePool<<E::G as Game>::M>,
+    outcome_pool: MovePool<(<E::G as Game>::M, f64)>,
+    prev_value: Evaluation,
+    eval: E,
+    opts: ExpectiminimaxOptions,
+}
+
+impl<E: Evaluator> Expectiminimax<E>
+where
+    E::G: ChanceGame,
+    <E::G as Game>::M: Copy,
+{
+    pub fn new(eval: E, opts: ExpectiminimaxOptions) -> Expectiminimax<E> {
+        Expectiminimax {
+            max_depth: 4,
+            move_pool: MovePool::default(),
+            outcome_pool: MovePool::default(),
+            prev_value: 0,
+            eval,
+            opts,
+        }
+    }
+
+    /// Set the maximum number of player moves to search. Chance nodes do
+    /// not count towards the depth.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+
+    #[doc(hidden)]
+    pub fn root_value(&self) -> Evaluation {
+        self.prev_value
+    }
+
+    /// The result of the last search for the player to move: a forced win
+    /// or loss, or else the expected evaluation of the root.
+    pub fn root_score(&self) -> Score {
+        Score::from(self.prev_value)
+    }
+
+    fn bound(&self, value: Evaluation) -> Evaluation {
+        min(max(value, -self.opts.eval_bound), self.opts.eval_bound)
+    }
+
+    // Recursively compute the value of the state, ply moves and outcomes
+    // from the root. The result is fail-soft: a value at or below alpha is
+    // an upper bound, and a value at or above beta is a lower bound.
+    fn search(
+        &mut self, s: &mut <E::G as Game>::S, depth: usize, ply: usize, alpha: Evaluation,
+        beta: Evaluation,
+    ) -> Evaluation {
+        if let Some(winner) = E::G::get_winner(s) {
+            return winner.evaluate_at_ply(ply);
+        }
+        if depth == 0 {
+            return self.bound(self.eval.evaluate(s));
+        }
+
+        let mut outcomes = self.outcome_pool.alloc();
+        E::G::generate_outcomes(s, &mut outcomes);
+        let value = if outcomes.is_empty() {
+            self.player_search(s, depth, ply, alpha, beta, None)
+        } else {
+            self.chance_search(s, depth, ply, alpha, beta, &outcomes)
+        };
+        self.outcome_pool.free(outcomes);
+        value
+    }
+
+    // Negamax at a non-terminal player node. If the value of the first move
+    // is already known, it is not searched again.
+    fn player_search(
+        &mut self, s: &mut <E::G as Game>::S, depth: usize, ply: usize, mut alpha: Evaluation,
+        beta: Evaluation, first_value: Option<Evaluation>,
+    ) -> Evaluation {
+        let mut moves = self.move_pool.alloc();
+        E::G::generate_moves(s, &mut moves);
+        if moves.is_empty() {
+            self.move_pool.free(moves);
+            return WORST_EVAL + ply as Evaluation;
+        }
+        let mut best = WORST_EVAL;
+        for (i, m) in moves.iter().enumerate() {
+            let value = match first_value {
+                Some(value) if i == 0 => value,
+                _ => {
+                    m.apply(s);
+                    let value = -self.search(s, depth - 1, ply + 1, -beta, -alpha);
+                    m.undo(s);
+                    value
+                }
+            };
+            best = max(best, value);
+            alpha = max(alpha, value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        self.move_pool.free(moves);
+        best
+    }
+
+    // Find a cheap lower bound on the value of the state by only searching
+    // its first move, with a window from the minimum value up to beta.
+    // Also returns the value of the first move, when one was searched.
+    fn probe(
+        &mut self, s: &mut <E::G as Game>::S, depth: usize, ply: usize, beta: Evaluation,
+    ) -> (Evaluation, Option<Evaluation>) {
+        let lower = -self.opts.eval_bound;
+        if E::G::get_winner(s).is_some() || depth == 0 {
+            return (self.search(s, depth, ply, lower, beta), None);
+        }
+        let mut outcomes = self.outcome_pool.alloc();
+        E::G::generate_outcomes(s, &mut outcomes);
+        let is_chance = !outcomes.is_empty();
+        self.outcome_pool.free(outcomes);
+        if is_chance {
+            // No cheap bound on consecutive chance nodes.
+            return (lower, None);
+        }
+
+        let mut moves = self.move_pool.alloc();
+        E::G::generate_moves(s, &mut moves);
+        let result = match moves.first() {
+            Some(&m) => {
+                m.apply(s);
+                // Nothing is below the minimum value, so unless this fails
+                // high, the value is exact.
+                let value = -self.search(s, depth - 1, ply + 1, -beta, -lower);
+                m.undo(s);
+                (value, Some(value))
+            }
+            None => (lower, None),
+        };
+        self.move_pool.free(moves);
+        result
+    }
+
+    // Compute the weighted average of the outcomes, with Star1 or Star2
+    // cutoffs if enabled. The value of each outcome is clamped to the eval
+    // bound. All arithmetic is done in floating point, and windows passed
+    // to the children are rounded outwards so that their bounds remain
+    // valid.
+    fn chance_search(
+        &mut self, s: &mut <E::G as Game>::S, depth: usize, ply: usize, alpha: Evaluation,
+        beta: Evaluation, outcomes: &[(<E::G as Game>::M, f64)],
+    ) -> Evaluation {
+        let upper = self.opts.eval_bound as f64;
+        let lower = -upper;
+        let (alpha, beta) = if self.opts.pruning == StarPruning::None {
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            (alpha as f64, beta as f64)
+        };
+
+        // Lower bounds on each outcome's value.
+        let mut lower_bounds = vec![lower; outcomes.len()];
+        // Values of the first move of each outcome, found while probing.
+        let mut first_values = vec![None; outcomes.len()];
+        // Probability-weighted sum of the lower bounds of the outcomes after
+        // the current one.
+        let mut rest_lower: f64 = outcomes.iter().map(|&(_, p)| p * lower).sum();
+
+        if self.opts.pruning == StarPruning::Star2 {
+            // Probing phase.
+            let mut sum = 0.0;
+            for (i, &(m, p)) in outcomes.iter().enumerate() {
+                rest_lower -= p * lower;
+                let child_beta = (beta - sum - rest_lower) / p;
+                if child_beta <= lower {
+                    // Already proven to be at or above beta.
+                    return round_eval(sum + p * lower + rest_lower);
+                }
+                m.apply(s);
+                let (value, first_value) =
+                    self.probe(s, depth, ply + 1, to_eval(child_beta.ceil()));
+                m.undo(s);
+                let value = self.bound(value) as f64;
+                if value >= child_beta {
+                    return round_eval(sum + p * value + rest_lower);
+                }
+                lower_bounds[i] = value;
+                first_values[i] = first_value;
+                sum += p * value;
+            }
+            rest_lower = sum;
+        }
+
+        // Probability-weighted sum of the exact values of searched outcomes.
+        let mut sum = 0.0;
+        // Total probability of the outcomes after the current one.
+        let mut rest_prob: f64 = outcomes.iter().map(|&(_, p)| p).sum();
+        for (i, &(m, p)) in outcomes.iter().enumerate() {
+            rest_prob -= p;
+            rest_lower -= p * lower_bounds[i];
+            let child_alpha = (alpha - sum - upper * rest_prob) / p;
+            let child_beta = (beta - sum - rest_lower) / p;
+            if child_alpha >= upper {
+                // This and the remaining outcomes can't raise the value above alpha.
+                return round_eval(sum + upper * (p + rest_prob));
+            }
+            if child_beta <= lower_bounds[i] {
+                // Already proven to be at or above beta.
+                return round_eval(sum + p * lower_bounds[i] + rest_lower);
+            }
+            let child_alpha_eval = to_eval(child_alpha.floor().max(lower_bounds[i] - 1.0));
+            let child_beta_eval = to_eval(child_beta.ceil());
+            m.apply(s);
+            let value = match first_values[i] {
+                Some(first_value) => self.player_search(
+                    s,
+                    depth,
+                    ply + 1,
+                    child_alpha_eval,
+                    child_beta_eval,
+                    Some(first_value),
+                ),
+                None => self.search(s, depth, ply + 1, child_alpha_eval, child_beta_eval),
+            };
+            let value = self.bound(value) as f64;
+            m.undo(s);
+            if value <= child_alpha {
+                // Fail low, return an upper bound.
+                return round_eval(sum + p * value + upper * rest_prob);
+            }
+            if value >= child_beta {
+                // Fail high, return a lower bound.
+                return round_eval(sum + p * value + rest_lower);
+            }
+            sum += p * value;
+        }
+        round_eval(sum)
+    }
+}
+
+// Convert a window bound to an evaluation that is safe to negate.
+fn to_eval(value: f64) -> Evaluation {
+    value.max(WORST_EVAL as f64).min(BEST_EVAL as f64) as Evaluation
+}
+
+fn round_eval(value: f64) -> Evaluation {
+    to_eval(value.round())
+}
+
+impl<E: Evaluator> Strategy<E::G> for Expectiminimax<E>
+where
+    E::G: ChanceGame,
+    <E::G as Game>::S: Clone,
+    <E::G as Game>::M: Copy,
+{
+    /// Choose a move for the player to move. Returns `None` at chance nodes,
+    /// where no player gets to choose.
+    fn choose_move(&mut self, s: &<E::G as Game>::S) -> Option<<E::G as Game>::M> {
+        let mut outcomes = Vec::new();
+        E::G::generate_outcomes(s, &mut outcomes);
+        if !outcomes.is_empty() {
+            return None;
+        }
+
+        let mut best = WORST_EVAL;
+        let mut moves = self.move_pool.alloc();
+        E::G::generate_moves(s, &mut moves);
+        let mut best_move = *moves.first()?;
+        let mut s_clone = s.clone();
+        for &m in moves.iter() {
+            m.apply(&mut s_clone);
+            let value = -self.search(&mut s_clone, self.max_depth, 1, WORST_EVAL, -best);
+            m.undo(&mut s_clone);
+            // Strictly better than any move found so far.
+            if value > best {
+                best = value;
+                best_move = m;
+            }
+        }
+        self.move_pool.free(moves);
+        self.prev_value = best;
+        Some(best_move)
+    }
+}
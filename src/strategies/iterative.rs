@@ -6,10 +6,16 @@
 
 use super::super::interface::*;
 use super::super::util::*;
+use super::ordering::MoveOrdering;
+use super::ponder::Ponderable;
+use super::progress::{Progress, SearchProgress, TICK_NODES};
+use super::stats::SearchStats;
 use super::table::*;
-use super::util::*;
+use super::time::{SearchLimits, TimeControl, TimeManager};
 
-use std::cmp::max;
+use std::cmp::{max, min};
+use std::io::{self, Read, Write};
+use std::mem::replace;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use instant::{Duration, Instant};
@@ -20,87 +26,107 @@ pub enum Replacement {
     Always,
     DepthPreferred,
     TwoTier,
-    // TODO: Bucket(size)
+    /// Buckets of this many adjacent entries. An entry replaces the entry
+    /// for the same position, or else the least valuable entry of its
+    /// bucket, preferring to keep deep entries from recent searches. Lookups
+    /// check the whole bucket.
+    ///
+    /// The table starts on a cache line, but buckets are not padded, so a
+    /// bucket only stays within cache lines when its size in bytes divides
+    /// 64, e.g. four 16-byte entries.
+    Bucket(usize),
 }
 
-struct TranspositionTable<M> {
-    table: Vec<Entry<M>>,
-    mask: usize,
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// Rules for scoring repeated positions as draws, for games where positions
+/// can repeat.
+pub enum RepetitionRule {
+    /// Don't look for repetitions.
+    Ignore,
+    /// A position is a draw if it occurred before, in the search or in the
+    /// game history.
+    Twofold,
+    /// A position is a draw if it occurred twice before.
+    Threefold,
+}
+
+pub(super) struct TranspositionTable<M> {
+    slots: Slots<Slot<Option<M>>, Slot<PackedMove>, M>,
+    buckets: Buckets,
     // Incremented for each iterative deepening run.
     // Values from old generations are always overwritten.
     generation: u8,
-    strategy: Replacement,
 }
 
-impl<M: Copy> TranspositionTable<M> {
-    fn new(table_byte_size: usize, strategy: Replacement) -> Self {
-        let size = (table_byte_size / std::mem::size_of::<Entry<M>>()).next_power_of_two();
-        let mask = if strategy == Replacement::TwoTier { (size - 1) & !1 } else { size - 1 };
-        let mut table = Vec::with_capacity(size);
-        for _ in 0..size {
-            table.push(Entry::<M> {
-                hash: 0,
-                value: 0,
-                depth: 0,
-                flag: EntryFlag::Exact,
-                generation: 0,
-                best_move: None,
-            });
-        }
-        Self { table, mask, generation: 0, strategy }
+impl<M> TranspositionTable<M> {
+    pub(super) fn new(table_byte_size: usize, strategy: Replacement) -> Self {
+        let (buckets, table) = Buckets::new_table(table_byte_size, strategy, Slot::empty);
+        Self { slots: Slots::Unpacked(table), buckets, generation: 0 }
+    }
+
+    pub(super) fn new_packed(table_byte_size: usize, strategy: Replacement) -> Self
+    where
+        M: PackableMove,
+    {
+        let (buckets, table) = Buckets::new_table(table_byte_size, strategy, Slot::empty);
+        Self { slots: Slots::Packed(table, Packer::new()), buckets, generation: 0 }
     }
 }
 
-impl<M: Copy> Table<M> for TranspositionTable<M> {
-    fn lookup(&self, hash: u64) -> Option<Entry<M>> {
-        let index = (hash as usize) & self.mask;
-        let entry = &self.table[index];
-        if hash == entry.hash {
-            Some(*entry)
-        } else if self.strategy == Replacement::TwoTier {
-            let entry = &self.table[index + 1];
-            if hash == entry.hash {
-                Some(*entry)
-            } else {
-                None
+impl<M: Move + Copy + SerializableMove> TranspositionTable<M> {
+    pub(super) fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let entries = match &self.slots {
+            Slots::Unpacked(table) => self.buckets.entries(table, &Unpacked),
+            Slots::Packed(table, packer) => self.buckets.entries(table, packer),
+        };
+        write_entries(w, self.generation, self.buckets.index_bits(), &entries)
+    }
+
+    // Read entries written by write_to into empty slots, or over shallower
+    // entries when the table is full.
+    pub(super) fn read_from<R: Read>(&mut self, r: &mut R) -> io::Result<()> {
+        let (generation, known_bits, entries) = read_entries(r)?;
+        self.generation = generation;
+        for entry in entries.iter() {
+            match &mut self.slots {
+                Slots::Unpacked(table) => {
+                    for (i, slot) in self.buckets.load(table, &Unpacked, entry, known_bits) {
+                        table[i] = slot;
+                    }
+                }
+                Slots::Packed(table, packer) => {
+                    for (i, slot) in self.buckets.load(table, packer, entry, known_bits) {
+                        table[i] = slot;
+                    }
+                }
             }
-        } else {
-            None
+        }
+        Ok(())
+    }
+}
+
+impl<M: Move + Copy> Table<M> for TranspositionTable<M> {
+    fn lookup(&self, hash: u64) -> Option<Entry<M>> {
+        match &self.slots {
+            Slots::Unpacked(table) => self.buckets.lookup(table, &Unpacked, hash),
+            Slots::Packed(table, packer) => self.buckets.lookup(table, packer, hash),
         }
     }
 
     fn store(&mut self, hash: u64, value: Evaluation, depth: u8, flag: EntryFlag, best_move: M) {
-        let dest = match self.strategy {
-            Replacement::Always => Some((hash as usize) & self.mask),
-            Replacement::DepthPreferred => {
-                let index = (hash as usize) & self.mask;
-                let entry = &self.table[index];
-                if entry.generation != self.generation || entry.depth <= depth {
-                    Some(index)
-                } else {
-                    None
+        let generation = self.generation;
+        match &mut self.slots {
+            Slots::Unpacked(table) => {
+                if let Some(i) = self.buckets.replace(table, hash, depth, generation) {
+                    table[i] = Slot::new(hash, value, depth, flag, generation, Some(best_move));
                 }
             }
-            Replacement::TwoTier => {
-                // index points to the first of a pair of entries, the depth-preferred entry and the always-replace entry.
-                let index = (hash as usize) & self.mask;
-                let entry = &self.table[index];
-                if entry.generation != self.generation || entry.depth <= depth {
-                    Some(index)
-                } else {
-                    Some(index + 1)
+            Slots::Packed(table, packer) => {
+                if let Some(i) = self.buckets.replace(table, hash, depth, generation) {
+                    let best_move = packer.store(best_move);
+                    table[i] = Slot::new(hash, value, depth, flag, generation, best_move);
                 }
             }
-        };
-        if let Some(index) = dest {
-            self.table[index] = Entry {
-                hash,
-                value,
-                depth,
-                flag,
-                generation: self.generation,
-                best_move: Some(best_move),
-            }
         }
     }
 
@@ -109,15 +135,106 @@ impl<M: Copy> Table<M> for TranspositionTable<M> {
     }
 }
 
+/// Settings for [late move
+/// reductions](https://www.chessprogramming.org/Late_Move_Reductions), which
+/// search moves late in the move ordering to a reduced depth, and only
+/// re-search them at full depth if they turn out better than expected.
+///
+/// This prunes the tree much more aggressively, at the risk of missing
+/// good moves that are ordered late.
+#[derive(Clone, Copy)]
+pub struct LateMoveReductions {
+    min_depth: u8,
+    full_depth_moves: usize,
+    reduce_noisy_moves: bool,
+    reduction: fn(u8, usize) -> u8,
+}
+
+// Reduce by about log(depth) * log(move_number), so that the latest moves
+// of the deepest searches are reduced the most.
+fn default_reduction(depth: u8, move_number: usize) -> u8 {
+    (0.75 + (depth as f64).ln() * (move_number as f64).ln() / 2.25) as u8
+}
+
+impl LateMoveReductions {
+    pub fn new() -> Self {
+        LateMoveReductions {
+            min_depth: 3,
+            full_depth_moves: 3,
+            reduce_noisy_moves: false,
+            reduction: default_reduction,
+        }
+    }
+}
+
+impl Default for LateMoveReductions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LateMoveReductions {
+    /// The minimum remaining depth at which moves are reduced.
+    pub fn with_min_depth(mut self, depth: u8) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// How many moves at the start of the move ordering are always
+    /// searched to full depth.
+    pub fn with_full_depth_moves(mut self, moves: usize) -> Self {
+        self.full_depth_moves = moves;
+        self
+    }
+
+    /// Whether to also reduce moves returned by `generate_noisy_moves`. By
+    /// default, noisy moves are always searched to full depth.
+    pub fn with_noisy_move_reductions(mut self, reduce: bool) -> Self {
+        self.reduce_noisy_moves = reduce;
+        self
+    }
+
+    /// How much to reduce the depth of a move, given the remaining depth and
+    /// the index of the move in the move ordering. Only consulted for depths
+    /// and moves that are eligible for reduction. The default reduction is
+    /// logarithmic in both.
+    pub fn with_reduction_table(mut self, reduction: fn(u8, usize) -> u8) -> Self {
+        self.reduction = reduction;
+        self
+    }
+
+    // Precompute the reduction for each depth and move index.
+    fn table(&self) -> Vec<u8> {
+        let mut table = Vec::with_capacity(256 * MAX_REDUCTION_MOVES);
+        for depth in 0..=u8::MAX {
+            for move_number in 0..MAX_REDUCTION_MOVES {
+                table.push((self.reduction)(depth, move_number));
+            }
+        }
+        table
+    }
+}
+
+// Moves beyond this index are reduced as much as this index.
+const MAX_REDUCTION_MOVES: usize = 64;
+
 /// Options to use for the iterative search engine.
 #[derive(Clone, Copy)]
 pub struct IterativeOptions {
-    table_byte_size: usize,
-    strategy: Replacement,
-    null_window_search: bool,
-    aspiration_window: Option<Evaluation>,
-    step_increment: u8,
-    max_quiescence_depth: u8,
+    pub(super) table_byte_size: usize,
+    pub(super) strategy: Replacement,
+    pub(super) null_window_search: bool,
+    pub(super) aspiration_window: Option<Evaluation>,
+    pub(super) step_increment: u8,
+    pub(super) max_quiescence_depth: u8,
+    pub(super) delta_pruning: bool,
+    pub(super) repetition_rule: RepetitionRule,
+    pub(super) late_move_reductions: Option<LateMoveReductions>,
+    pub(super) null_move_reduction: Option<u8>,
+    pub(super) killer_moves: bool,
+    pub(super) history_heuristic: bool,
+    pub(super) countermove_heuristic: bool,
+    pub(super) multi_pv: usize,
 }
 
 impl IterativeOptions {
@@ -129,6 +246,14 @@ impl IterativeOptions {
             aspiration_window: None,
             step_increment: 1,
             max_quiescence_depth: 0,
+            delta_pruning: false,
+            repetition_rule: RepetitionRule::Ignore,
+            late_move_reductions: None,
+            null_move_reduction: None,
+            killer_moves: true,
+            history_heuristic: true,
+            countermove_heuristic: true,
+            multi_pv: 1,
         }
     }
 }
@@ -182,22 +307,124 @@ impl IterativeOptions {
         self.max_quiescence_depth = depth;
         self
     }
+
+    /// Enable [delta
+    /// pruning](https://www.chessprogramming.org/Delta_Pruning) in
+    /// quiescence search, which skips noisy moves that cannot raise the
+    /// evaluation above the best value already found. The Game must
+    /// implement `delta_pruning_margin` for this to have any effect.
+    pub fn with_delta_pruning(mut self) -> Self {
+        self.delta_pruning = true;
+        self
+    }
+
+    /// Score repeated positions as draws according to the rule. Results
+    /// that depend on the path to a position are not stored in the
+    /// transposition table, but results stored along one path may still be
+    /// reused along another that would have repeated differently.
+    pub fn with_repetition_rule(mut self, rule: RepetitionRule) -> Self {
+        self.repetition_rule = rule;
+        self
+    }
+
+    /// Enable late move reductions, which trade some accuracy for a much
+    /// deeper search.
+    pub fn with_late_move_reductions(mut self, lmr: LateMoveReductions) -> Self {
+        self.late_move_reductions = Some(lmr);
+        self
+    }
+
+    /// Enable [null-move
+    /// pruning](https://www.chessprogramming.org/Null_Move_Pruning), which
+    /// cuts off positions where passing the turn is already too good for the
+    /// opponent to allow, according to a search reduced by an extra
+    /// `depth_reduction` plies (R). The Game must implement `null_move` for
+    /// this to have any effect.
+    pub fn with_null_move_pruning(mut self, depth_reduction: u8) -> Self {
+        self.null_move_reduction = Some(depth_reduction);
+        self
+    }
+
+    /// Whether to first try the moves that most recently caused a cutoff at
+    /// the same depth in other branches. Also known as the killer heuristic.
+    pub fn with_killer_moves(mut self, killers: bool) -> Self {
+        self.killer_moves = killers;
+        self
+    }
+
+    /// Whether to order moves by how often they caused cutoffs anywhere in
+    /// the search tree. The Game must implement `move_index` for this to
+    /// have any effect.
+    pub fn with_history_heuristic(mut self, history: bool) -> Self {
+        self.history_heuristic = history;
+        self
+    }
+
+    /// Whether to first try the move that most recently refuted the
+    /// opponent's previous move. The Game must implement `move_index` for
+    /// this to have any effect.
+    pub fn with_countermove_heuristic(mut self, countermoves: bool) -> Self {
+        self.countermove_heuristic = countermoves;
+        self
+    }
+
+    /// Find the best `k` moves of the root, each with its own score and
+    /// principal variation, by searching the root again on each iteration
+    /// for each move after the first, excluding the moves already found.
+    pub fn with_multi_pv(mut self, k: usize) -> Self {
+        self.multi_pv = k;
+        self
+    }
+
+    fn move_ordering<G: Game>(&self) -> MoveOrdering<G> {
+        MoveOrdering::new(self.killer_moves, self.history_heuristic, self.countermove_heuristic)
+    }
 }
 
+// A move of the root, with its score and principal variation.
+pub(super) type Line<M> = (M, Score, Vec<M>);
+
 pub(super) struct Negamaxer<E: Evaluator, T> {
     timeout: Arc<AtomicBool>,
+    // Set from outside the search.
+    stop: Arc<AtomicBool>,
+    node_limit: u64,
+    // Nodes explored since the limits were set.
+    pub(super) search_nodes: u64,
+    pub(super) progress: Progress<<E::G as Game>::M>,
     pub(super) table: T,
+    pub(super) ordering: MoveOrdering<E::G>,
     move_pool: MovePool<<E::G as Game>::M>,
     eval: E,
+    // Moves from the root of the search to the current node.
+    pub(super) line: Vec<<E::G as Game>::M>,
+    // Hashes of the game history, then of the positions from the root of
+    // the search to the parent of the current node.
+    pub(super) path: Vec<u64>,
+    // Number of positions in the path before the root.
+    history_len: usize,
+    // Lowest index into the path of a position that was repeated in the
+    // current subtree.
+    repetition_index: usize,
+    // Moves the root may choose from, and further moves to skip there for
+    // multi-PV. The root isn't stored in the table while any are skipped,
+    // and may be replaced there anyway, so its best move is kept here too.
+    pub(super) root_moves: RootMoves<<E::G as Game>::M>,
+    excluded_moves: Vec<<E::G as Game>::M>,
+    root_best_move: Option<<E::G as Game>::M>,
 
     // Config
-    max_quiescence_depth: u8,
-    null_window_search: bool,
+    opts: IterativeOptions,
+    // Late move reductions by depth and move index.
+    reduction_table: Vec<u8>,
 
     // Stats
-    nodes_explored: u64,
-    total_generate_move_calls: u64,
-    total_generated_moves: u64,
+    pub(super) nodes_explored: u64,
+    pub(super) total_generate_move_calls: u64,
+    pub(super) total_generated_moves: u64,
+    pub(super) beta_cutoffs: u64,
+    pub(super) first_move_cutoffs: u64,
+    pub(super) table_hits: u64,
 }
 
 impl<E: Evaluator, T: Table<<E::G as Game>::M>> Negamaxer<E, T>
@@ -205,19 +432,33 @@ where
     <E::G as Game>::S: Zobrist,
     <E::G as Game>::M: Copy + Eq,
 {
-    pub(super) fn new(
-        table: T, eval: E, max_quiescence_depth: u8, null_window_search: bool,
-    ) -> Self {
+    // Only the search options are used, the table is created by the caller.
+    pub(super) fn new(table: T, eval: E, opts: IterativeOptions) -> Self {
         Self {
             timeout: Arc::new(AtomicBool::new(false)),
+            stop: Arc::new(AtomicBool::new(false)),
+            node_limit: u64::MAX,
+            search_nodes: 0,
+            progress: Progress::new(),
             table,
+            ordering: opts.move_ordering(),
             eval,
             move_pool: MovePool::default(),
-            max_quiescence_depth,
-            null_window_search,
+            line: Vec::new(),
+            path: Vec::new(),
+            history_len: 0,
+            repetition_index: usize::MAX,
+            root_moves: RootMoves::All,
+            excluded_moves: Vec::new(),
+            root_best_move: None,
+            opts,
+            reduction_table: opts.late_move_reductions.map_or(Vec::new(), |lmr| lmr.table()),
             nodes_explored: 0,
             total_generate_move_calls: 0,
             total_generated_moves: 0,
+            beta_cutoffs: 0,
+            first_move_cutoffs: 0,
+            table_hits: 0,
         }
     }
 
@@ -225,46 +466,150 @@ where
         self.timeout = timeout;
     }
 
-    fn reset_stats(&mut self) {
+    // Set the stop token and node limit for the next search.
+    pub(super) fn set_limits(&mut self, limits: &SearchLimits) {
+        self.stop = limits.stop_token();
+        self.node_limit = limits.node_limit();
+        self.search_nodes = 0;
+    }
+
+    fn stopped(&self) -> bool {
+        self.timeout.load(Ordering::Relaxed) || self.stop.load(Ordering::Relaxed)
+    }
+
+    // Set the hashes of the positions played before the root, oldest first.
+    pub(super) fn set_game_history(&mut self, history: &[u64]) {
+        self.path.clear();
+        self.path.extend_from_slice(history);
+        self.history_len = history.len();
+    }
+
+    // If the position occurred before in the path, returns the index into
+    // the path of the earliest occurrence its value depends on, and whether
+    // it's a draw by repetition.
+    fn repetition(&self, hash: u64) -> Option<(usize, bool)> {
+        let repeats = match self.opts.repetition_rule {
+            RepetitionRule::Ignore => return None,
+            RepetitionRule::Twofold => 1,
+            RepetitionRule::Threefold => 2,
+        };
+        // The root needs a move, even if it repeats the game history.
+        if self.path.len() == self.history_len {
+            return None;
+        }
+        let mut last = None;
+        let mut count = 0;
+        for (i, &h) in self.path.iter().enumerate().rev() {
+            if h == hash {
+                count += 1;
+                if count == repeats {
+                    return Some((i, true));
+                }
+                last = last.or(Some(i));
+            }
+        }
+        last.map(|i| (i, false))
+    }
+
+    pub(super) fn reset_stats(&mut self) {
         self.nodes_explored = 0;
         self.total_generate_move_calls = 0;
         self.total_generated_moves = 0;
+        self.beta_cutoffs = 0;
+        self.first_move_cutoffs = 0;
+        self.table_hits = 0;
+    }
+
+    // Collect the counters since the last reset. The nodes of the current
+    // iteration are reported as partial.
+    pub(super) fn stats(
+        &self, depth: u8, nodes_per_depth: &[u64], wall_time: Duration,
+    ) -> SearchStats {
+        SearchStats {
+            depth,
+            nodes_per_depth: nodes_per_depth.to_vec(),
+            partial_nodes: self.nodes_explored,
+            table_hits: self.table_hits,
+            generate_move_calls: self.total_generate_move_calls,
+            generated_moves: self.total_generated_moves,
+            beta_cutoffs: self.beta_cutoffs,
+            first_move_cutoffs: self.first_move_cutoffs,
+            wall_time,
+            ..SearchStats::default()
+        }
     }
 
-    // Negamax only among noisy moves.
+    // Negamax only among noisy moves, where the player to move may also
+    // stand pat and accept the static evaluation.
     fn noisy_negamax(
-        &mut self, s: &mut <E::G as Game>::S, depth: u8, mut alpha: Evaluation, beta: Evaluation,
+        &mut self, s: &mut <E::G as Game>::S, depth: u8, ply: usize, mut alpha: Evaluation,
+        mut beta: Evaluation,
     ) -> Option<Evaluation> {
-        if self.timeout.load(Ordering::Relaxed) {
+        if self.stopped() {
             return None;
         }
         if let Some(winner) = E::G::get_winner(s) {
-            return Some(winner.evaluate());
+            return Some(winner.evaluate_at_ply(ply));
         }
         if depth == 0 {
             return Some(self.eval.evaluate(s));
         }
 
+        let alpha_orig = alpha;
+        let hash = s.zobrist_hash();
+        let mut good_move = None;
+        if let Some(value) = self.table.check(hash, 0, ply, &mut good_move, &mut alpha, &mut beta) {
+            self.table_hits += 1;
+            return Some(value);
+        }
+
+        // The static evaluation is a lower bound, as the player to move
+        // doesn't have to make a noisy move.
+        let stand_pat = self.eval.evaluate(s);
+        if stand_pat >= beta {
+            return Some(stand_pat);
+        }
+        alpha = max(alpha, stand_pat);
+
         let mut moves = self.move_pool.alloc();
         E::G::generate_noisy_moves(s, &mut moves);
-        if moves.is_empty() {
-            // Only quiet moves remain, return leaf evaluation.
-            self.move_pool.free(moves);
-            return Some(self.eval.evaluate(s));
+        if let Some(good) = good_move {
+            // Rearrange so predicted good move is first.
+            if let Some(i) = moves.iter().position(|&m| m == good) {
+                moves.swap(0, i);
+            }
         }
 
-        let mut best = WORST_EVAL;
-        for m in moves.iter() {
+        let mut best = stand_pat;
+        let mut best_move = None;
+        for &m in moves.iter() {
+            if self.opts.delta_pruning {
+                if let Some(margin) = E::G::delta_pruning_margin(s, &m) {
+                    // Keep the result an upper bound of the skipped move.
+                    let bound = stand_pat.saturating_add(margin);
+                    if bound <= alpha {
+                        best = max(best, bound);
+                        continue;
+                    }
+                }
+            }
             m.apply(s);
-            let value = -self.noisy_negamax(s, depth - 1, -beta, -alpha)?;
+            let value = -self.noisy_negamax(s, depth - 1, ply + 1, -beta, -alpha)?;
             m.undo(s);
-            best = max(best, value);
+            if value > best {
+                best = value;
+                best_move = Some(m);
+            }
             alpha = max(alpha, value);
             if alpha >= beta {
                 break;
             }
         }
         self.move_pool.free(moves);
+        // Standing pat has no move to remember.
+        if let Some(best_move) = best_move {
+            self.table.update(hash, alpha_orig, beta, 0, ply, best, best_move);
+        }
         Some(best)
     }
 
@@ -273,62 +618,127 @@ where
         &mut self, s: &mut <E::G as Game>::S, depth: u8, mut alpha: Evaluation,
         mut beta: Evaluation,
     ) -> Option<Evaluation> {
-        if self.timeout.load(Ordering::Relaxed) {
+        if self.stopped() || self.search_nodes >= self.node_limit {
             return None;
         }
 
         self.nodes_explored += 1;
+        self.search_nodes += 1;
+        if self.search_nodes.is_multiple_of(TICK_NODES) {
+            self.progress.tick(self.search_nodes);
+        }
 
+        let hash = s.zobrist_hash();
+        let root = self.path.len() == self.history_len;
+        if root {
+            self.root_best_move = None;
+        }
+        let repetition = self.repetition(hash);
+        if let Some((index, draw)) = repetition {
+            self.repetition_index = min(self.repetition_index, index);
+            if draw {
+                return Some(0);
+            }
+        }
+        let ply = self.line.len();
         if depth == 0 {
             // Evaluate quiescence search on leaf nodes.
             // Will just return the node's evaluation if quiescence search is disabled.
-            return self.noisy_negamax(s, self.max_quiescence_depth, alpha, beta);
+            return self.noisy_negamax(s, self.opts.max_quiescence_depth, ply, alpha, beta);
         }
         if let Some(winner) = E::G::get_winner(s) {
-            return Some(winner.evaluate());
+            return Some(winner.evaluate_at_ply(ply));
+        }
+        // Mate-distance pruning: no result here can beat a win already found
+        // closer to the root. The root is always searched, so its move is
+        // stored.
+        if ply > 0 {
+            alpha = max(alpha, WORST_EVAL + ply as Evaluation);
+            beta = min(beta, BEST_EVAL - ply as Evaluation - 1);
+            if alpha >= beta {
+                return Some(alpha);
+            }
         }
 
         let alpha_orig = alpha;
-        let hash = s.zobrist_hash();
         let mut good_move = None;
-        if let Some(value) = self.table.check(hash, depth, &mut good_move, &mut alpha, &mut beta) {
+        let filtering = root && self.filtering_root();
+        if repetition.is_some()
+            || filtering
+            || (root && self.opts.repetition_rule != RepetitionRule::Ignore)
+        {
+            // Values in the table may have been found without the earlier
+            // occurrences of this position, or with a different game history
+            // or other moves at the root, so only use the move.
+            good_move = self.table.lookup(hash).and_then(|entry| entry.best_move);
+        } else if let Some(value) =
+            self.table.check(hash, depth, ply, &mut good_move, &mut alpha, &mut beta)
+        {
+            self.table_hits += 1;
+            if root {
+                self.root_best_move = good_move;
+            }
             return Some(value);
         }
+        if self.null_move_cutoff(s, depth, beta)? {
+            return Some(beta);
+        }
 
         let mut moves = self.move_pool.alloc();
         E::G::generate_moves(s, &mut moves);
+        if filtering {
+            moves.retain(|m| self.root_moves.allows(m) && !self.excluded_moves.contains(m));
+        }
         self.total_generate_move_calls += 1;
         self.total_generated_moves += moves.len() as u64;
         if moves.is_empty() {
             self.move_pool.free(moves);
-            return Some(WORST_EVAL);
+            return Some(WORST_EVAL + ply as Evaluation);
         }
+        let mut first_unordered = 0;
         if let Some(good) = good_move {
             // Rearrange so predicted good move is first.
             for i in 0..moves.len() {
                 if moves[i] == good {
                     moves.swap(0, i);
+                    first_unordered = 1;
                     break;
                 }
             }
         }
+        let prev_move = self.line.last().copied();
+        self.ordering.order_moves(&mut moves[first_unordered..], ply, prev_move);
+
+        // Late moves are eligible for reduction, except noisy moves.
+        let mut first_reduced = moves.len();
+        let mut noisy_moves = self.move_pool.alloc();
+        if let Some(lmr) = self.opts.late_move_reductions {
+            if depth >= lmr.min_depth {
+                first_reduced = lmr.full_depth_moves;
+                if !lmr.reduce_noisy_moves {
+                    E::G::generate_noisy_moves(s, &mut noisy_moves);
+                }
+            }
+        }
 
         let mut best = WORST_EVAL;
         let mut best_move = moves[0];
         let mut null_window = false;
-        for &m in moves.iter() {
-            m.apply(s);
-            let value = if null_window {
-                let probe = -self.negamax(s, depth - 1, -alpha - 1, -alpha)?;
-                if probe > alpha && probe < beta {
-                    // Full search fallback.
-                    -self.negamax(s, depth - 1, -beta, -probe)?
-                } else {
-                    probe
-                }
+        let outer_repetition_index = replace(&mut self.repetition_index, usize::MAX);
+        for (i, &m) in moves.iter().enumerate() {
+            let reduction = if i >= first_reduced && !noisy_moves.contains(&m) {
+                self.reduction_table
+                    [depth as usize * MAX_REDUCTION_MOVES + i.min(MAX_REDUCTION_MOVES - 1)]
             } else {
-                -self.negamax(s, depth - 1, -beta, -alpha)?
+                0
             };
+            m.apply(s);
+            self.line.push(m);
+            self.path.push(hash);
+            let value = self.search_move(s, depth, reduction, alpha, beta, null_window);
+            self.path.pop();
+            self.line.pop();
+            let value = value?;
             m.undo(s);
             if value > best {
                 best = value;
@@ -338,16 +748,147 @@ where
                 alpha = value;
                 // Now that we've found a good move, assume following moves
                 // are worse, and seek to cull them without full evaluation.
-                null_window = self.null_window_search;
+                null_window = self.opts.null_window_search;
             }
             if alpha >= beta {
                 break;
             }
         }
+        if best >= beta {
+            self.beta_cutoffs += 1;
+            if best_move == moves[0] {
+                self.first_move_cutoffs += 1;
+            }
+            self.ordering.record_cutoff(best_move, depth, ply, prev_move);
+        }
 
-        self.table.update(hash, alpha_orig, beta, depth, best, best_move);
+        // A value that depends on repeating a position from before this node
+        // is only valid for the current path. The root is always stored, so
+        // later searches can start from its move, unless some of its moves
+        // were skipped.
+        let path_dependent = self.repetition_index < self.path.len();
+        self.repetition_index = min(self.repetition_index, outer_repetition_index);
+        if root {
+            self.root_best_move = Some(best_move);
+        }
+        if !filtering && (!path_dependent || root) {
+            self.table.update(hash, alpha_orig, beta, depth, ply, best, best_move);
+        }
         self.move_pool.free(moves);
-        Some(clamp_value(best))
+        self.move_pool.free(noisy_moves);
+        Some(best)
+    }
+
+    // Whether the position is still at least beta after passing the turn,
+    // according to a reduced-depth null-window search. Returns None if it
+    // hits the timeout.
+    fn null_move_cutoff(
+        &mut self, s: &mut <E::G as Game>::S, depth: u8, beta: Evaluation,
+    ) -> Option<bool> {
+        let reduction = match self.opts.null_move_reduction {
+            Some(reduction) if depth > reduction => reduction,
+            _ => return Some(false),
+        };
+        // Null moves can't prove wins or losses.
+        if !(WORST_EVAL + WIN_DISTANCE..=BEST_EVAL - WIN_DISTANCE).contains(&beta) {
+            return Some(false);
+        }
+        // Never pass at the root, which needs a real move, or twice in a row.
+        let null_move = match E::G::null_move(s) {
+            Some(m) if !self.line.is_empty() && self.line.last() != Some(&m) => m,
+            _ => return Some(false),
+        };
+        self.path.push(s.zobrist_hash());
+        null_move.apply(s);
+        self.line.push(null_move);
+        let value = self.negamax(s, depth - 1 - reduction, -beta, -beta + 1);
+        self.line.pop();
+        self.path.pop();
+        let value = -value?;
+        null_move.undo(s);
+        Some(value >= beta)
+    }
+
+    // Compute the value of a move that was just applied, from the
+    // perspective of the player who made it.
+    fn search_move(
+        &mut self, s: &mut <E::G as Game>::S, depth: u8, reduction: u8, alpha: Evaluation,
+        beta: Evaluation, null_window: bool,
+    ) -> Option<Evaluation> {
+        if reduction > 0 {
+            // Only search at full depth if the reduced search beats alpha.
+            let reduced_depth = (depth - 1).saturating_sub(reduction);
+            let probe = -self.negamax(s, reduced_depth, -alpha - 1, -alpha)?;
+            if probe <= alpha {
+                return Some(probe);
+            }
+        }
+        Some(if null_window {
+            let probe = -self.negamax(s, depth - 1, -alpha - 1, -alpha)?;
+            if probe > alpha && probe < beta {
+                // Full search fallback.
+                -self.negamax(s, depth - 1, -beta, -probe)?
+            } else {
+                probe
+            }
+        } else {
+            -self.negamax(s, depth - 1, -beta, -alpha)?
+        })
+    }
+
+    // Whether some moves of the root are skipped.
+    fn filtering_root(&self) -> bool {
+        self.root_moves != RootMoves::All || !self.excluded_moves.is_empty()
+    }
+
+    // The best move of the root and its value, after a search that returned
+    // value. None if the root has no moves to choose from.
+    pub(super) fn root_result(&self, value: Evaluation) -> Option<(<E::G as Game>::M, Evaluation)> {
+        self.root_best_move.map(|m| (m, value))
+    }
+
+    // The principal variation from the root through its move m, as deep as
+    // the table remembers it.
+    pub(super) fn populate_root_pv(
+        &self, pv: &mut Vec<<E::G as Game>::M>, s: &mut <E::G as Game>::S, m: <E::G as Game>::M,
+        depth: u8,
+    ) {
+        m.apply(s);
+        self.table.populate_pv(pv, s, depth.saturating_sub(1));
+        m.undo(s);
+        pv.insert(0, m);
+    }
+
+    // After a search of the root to depth found its best move and value,
+    // find its best count moves, each by searching again without the moves
+    // found before it. Returns each move with its score and principal
+    // variation, or None if it hits the timeout.
+    pub(super) fn best_lines(
+        &mut self, s: &mut <E::G as Game>::S, depth: u8, count: usize,
+        best: (<E::G as Game>::M, Evaluation),
+    ) -> Option<Vec<Line<<E::G as Game>::M>>> {
+        let mut moves = Vec::new();
+        E::G::generate_moves(s, &mut moves);
+        let count = min(count, moves.iter().filter(|m| self.root_moves.allows(m)).count());
+        let mut best = Some(best);
+        let mut lines = Vec::new();
+        while let Some((m, value)) = best {
+            let mut pv = Vec::new();
+            self.populate_root_pv(&mut pv, s, m, depth);
+            lines.push((m, Score::from(value), pv));
+            self.excluded_moves.push(m);
+            if lines.len() >= count {
+                break;
+            }
+            best = self
+                .negamax(s, depth, WORST_EVAL, BEST_EVAL)
+                .and_then(|value| self.root_result(value));
+        }
+        self.excluded_moves.clear();
+        if lines.len() < count {
+            return None;
+        }
+        Some(lines)
     }
 
     // Try to find the value within a window around the estimated value.
@@ -367,8 +908,7 @@ where
 }
 
 pub struct IterativeSearch<E: Evaluator> {
-    max_depth: usize,
-    max_time: Duration,
+    limits: SearchLimits,
     negamaxer: Negamaxer<E, TranspositionTable<<E::G as Game>::M>>,
     prev_value: Evaluation,
     opts: IterativeOptions,
@@ -379,8 +919,8 @@ pub struct IterativeSearch<E: Evaluator> {
     actual_depth: u8,
     // Nodes explored at each depth.
     nodes_explored: Vec<u64>,
-    table_hits: usize,
     pv: Vec<<E::G as Game>::M>,
+    lines: Vec<Line<<E::G as Game>::M>>,
     wall_time: Duration,
 }
 
@@ -391,54 +931,118 @@ where
 {
     pub fn new(eval: E, opts: IterativeOptions) -> IterativeSearch<E> {
         let table = TranspositionTable::new(opts.table_byte_size, opts.strategy);
-        let negamaxer =
-            Negamaxer::new(table, eval, opts.max_quiescence_depth, opts.null_window_search);
+        Self::from_table(eval, opts, table)
+    }
+
+    /// Like `new`, but the transposition table stores moves packed by
+    /// `PackableMove`, so that it holds more positions in the same memory.
+    pub fn new_packed(eval: E, opts: IterativeOptions) -> IterativeSearch<E>
+    where
+        <E::G as Game>::M: PackableMove,
+    {
+        let table = TranspositionTable::new_packed(opts.table_byte_size, opts.strategy);
+        Self::from_table(eval, opts, table)
+    }
+
+    fn from_table(
+        eval: E, opts: IterativeOptions, table: TranspositionTable<<E::G as Game>::M>,
+    ) -> IterativeSearch<E> {
+        let negamaxer = Negamaxer::new(table, eval, opts);
         IterativeSearch {
-            max_depth: 100,
-            max_time: Duration::from_secs(5),
+            limits: SearchLimits::new().with_time(Duration::from_secs(5)),
             prev_value: 0,
             negamaxer,
             opts,
             actual_depth: 0,
             nodes_explored: Vec::new(),
-            table_hits: 0,
             pv: Vec::new(),
+            lines: Vec::new(),
             wall_time: Duration::default(),
         }
     }
 
-    /// Set the maximum depth to search. Disables the timeout.
+    /// Set the maximum depth to search. Disables the timeout, see
+    /// `set_limits` to combine them.
     /// This can be changed between moves while reusing the transposition table.
     pub fn set_max_depth(&mut self, depth: usize) {
-        self.max_depth = depth;
-        self.max_time = Duration::new(0, 0);
+        self.limits = SearchLimits::new().with_depth(depth);
     }
 
     /// Set the maximum time to compute the best move. When the timeout is
     /// hit, it returns the best move found of the previous full
     /// iteration. Unlimited max depth.
     pub fn set_timeout(&mut self, max_time: Duration) {
-        self.max_time = max_time;
-        self.max_depth = 100;
+        self.limits = SearchLimits::new().with_time(max_time);
+    }
+
+    /// Budget the time for each move from the state of the game clock,
+    /// which should be updated before each move. Unlimited max depth.
+    pub fn set_time_control(&mut self, clock: TimeControl) {
+        self.limits = SearchLimits::new().with_time_control(clock);
+    }
+
+    /// Set any combination of limits, replacing those set before.
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    /// Call the callback with the progress of the search after each
+    /// completed iteration, and during iterations that take longer than
+    /// `interval`.
+    pub fn set_progress_callback<F>(&mut self, interval: Duration, callback: F)
+    where
+        F: FnMut(&SearchProgress<<E::G as Game>::M>) + Send + 'static,
+    {
+        self.negamaxer.progress.set_callback(interval, Box::new(callback));
     }
 
-    /// Return a human-readable summary of the last move generation.
-    pub fn stats(&self) -> String {
-        let total_nodes_explored: u64 = self.nodes_explored.iter().sum();
-        let mean_branching_factor = self.negamaxer.total_generated_moves as f64
-            / self.negamaxer.total_generate_move_calls as f64;
-        let effective_branching_factor = (*self.nodes_explored.last().unwrap_or(&0) as f64)
-            .powf((self.actual_depth as f64 + 1.0).recip());
-        let throughput = (total_nodes_explored + self.negamaxer.nodes_explored) as f64
-            / self.wall_time.as_secs_f64();
-        format!("Explored {} nodes to depth {}. MBF={:.1} EBF={:.1}\nPartial exploration of next depth hit {} nodes.\n{} transposition table hits.\n{} nodes/sec",
-		total_nodes_explored, self.actual_depth, mean_branching_factor, effective_branching_factor,
-		self.negamaxer.nodes_explored, self.table_hits, throughput as usize)
+    /// Set the Zobrist hashes of the positions played before the state
+    /// passed to the next `choose_move`, oldest first, so that the
+    /// repetition rule can count them.
+    pub fn set_game_history(&mut self, history: &[u64]) {
+        self.negamaxer.set_game_history(history);
+    }
+
+    /// Restrict the moves of the root that the following searches may
+    /// choose from, until replaced with `RootMoves::All`. The root isn't
+    /// stored in the table while restricted, so later searches of all its
+    /// moves aren't misled.
+    pub fn set_root_moves(&mut self, root_moves: RootMoves<<E::G as Game>::M>) {
+        self.negamaxer.root_moves = root_moves;
+    }
+
+    /// Return statistics about the last move generation.
+    pub fn stats(&self) -> SearchStats {
+        self.negamaxer.stats(self.actual_depth, &self.nodes_explored, self.wall_time)
+    }
+
+    /// Save the transposition table, so that a later search can resume the
+    /// analysis after `read_table`.
+    pub fn write_table<W: Write>(&self, w: &mut W) -> io::Result<()>
+    where
+        <E::G as Game>::M: SerializableMove,
+    {
+        self.negamaxer.table.write_to(w)
+    }
+
+    /// Load a table saved by `write_table`, possibly with a different table
+    /// size. Entries already in the table are kept where there's room.
+    pub fn read_table<R: Read>(&mut self, r: &mut R) -> io::Result<()>
+    where
+        <E::G as Game>::M: SerializableMove,
+    {
+        self.negamaxer.table.read_from(r)
     }
 
     #[doc(hidden)]
     pub fn root_value(&self) -> Evaluation {
-        unclamp_value(self.prev_value)
+        self.prev_value
+    }
+
+    /// The result of the last search for the player to move: a forced win
+    /// or loss, or else the evaluation of the root.
+    pub fn root_score(&self) -> Score {
+        Score::from(self.prev_value)
     }
 
     /// Return what the engine considered to be the best sequence of moves
@@ -446,6 +1050,16 @@ where
     pub fn principal_variation(&self) -> &[<E::G as Game>::M] {
         &self.pv[..]
     }
+
+    /// Return the best moves of the last search, up to the count set by
+    /// `with_multi_pv`, each with its score and principal variation, best
+    /// first.
+    pub fn principal_variations(&self) -> Vec<Line<<E::G as Game>::M>> {
+        if !self.lines.is_empty() {
+            return self.lines.clone();
+        }
+        self.pv.first().map(|&m| (m, self.root_score(), self.pv.clone())).into_iter().collect()
+    }
 }
 
 impl<E: Evaluator> Strategy<E::G> for IterativeSearch<E>
@@ -454,43 +1068,101 @@ where
     <E::G as Game>::M: Copy + Eq,
 {
     fn choose_move(&mut self, s: &<E::G as Game>::S) -> Option<<E::G as Game>::M> {
+        let (timeout, manager) = self.limits.start_timer();
+        self.search(s, timeout, manager)
+    }
+}
+
+impl<E: Evaluator> Ponderable<E::G> for IterativeSearch<E>
+where
+    <E::G as Game>::S: Clone + Zobrist,
+    <E::G as Game>::M: Copy + Eq,
+{
+    fn choose_move_until(
+        &mut self, s: &<E::G as Game>::S, stop: Arc<AtomicBool>,
+    ) -> Option<<E::G as Game>::M> {
+        self.search(s, stop, None)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.limits.expected_time()
+    }
+
+    fn principal_variation(&self) -> &[<E::G as Game>::M] {
+        &self.pv[..]
+    }
+}
+
+impl<E: Evaluator> IterativeSearch<E>
+where
+    <E::G as Game>::S: Clone + Zobrist,
+    <E::G as Game>::M: Copy + Eq,
+{
+    // Iteratively deepen until the maximum depth, the timeout signal, or the
+    // time manager decides to stop.
+    fn search(
+        &mut self, s: &<E::G as Game>::S, timeout: Arc<AtomicBool>,
+        mut manager: Option<TimeManager>,
+    ) -> Option<<E::G as Game>::M> {
         self.negamaxer.table.advance_generation();
+        self.negamaxer.ordering.new_search();
         // Reset stats.
         self.nodes_explored.clear();
         self.negamaxer.reset_stats();
         self.actual_depth = 0;
-        self.table_hits = 0;
+        self.lines.clear();
         let start_time = Instant::now();
-        // Start timer if configured.
-        self.negamaxer.set_timeout(if self.max_time == Duration::new(0, 0) {
-            Arc::new(AtomicBool::new(false))
-        } else {
-            timeout_signal(self.max_time)
-        });
+        self.negamaxer.set_timeout(timeout);
+        self.negamaxer.set_limits(&self.limits);
+        self.negamaxer.progress.start();
 
-        let root_hash = s.zobrist_hash();
         let mut s_clone = s.clone();
         let mut best_move = None;
 
-        let mut depth = self.max_depth as u8 % self.opts.step_increment;
-        while depth <= self.max_depth as u8 {
+        let max_depth = self.limits.max_depth() as u8;
+        let mut depth = max_depth % self.opts.step_increment;
+        while depth <= max_depth {
             if let Some(window) = self.opts.aspiration_window {
                 // Results of the search are stored in the table.
                 self.negamaxer.aspiration_search(&mut s_clone, depth + 1, self.prev_value, window);
             }
-            if self.negamaxer.negamax(&mut s_clone, depth + 1, WORST_EVAL, BEST_EVAL).is_none() {
+            let value = match self.negamaxer.negamax(&mut s_clone, depth + 1, WORST_EVAL, BEST_EVAL)
+            {
+                Some(value) => value,
                 // Timeout. Return the best move from the previous depth.
-                break;
+                None => break,
+            };
+            let (root_move, root_value) = match self.negamaxer.root_result(value) {
+                Some(result) => result,
+                None => break,
+            };
+            if self.opts.multi_pv > 1 {
+                let best = (root_move, root_value);
+                match self.negamaxer.best_lines(&mut s_clone, depth + 1, self.opts.multi_pv, best) {
+                    Some(lines) => self.lines = lines,
+                    None => break,
+                }
             }
-            let entry = self.negamaxer.table.lookup(root_hash).unwrap();
-            best_move = entry.best_move;
+            let best_move_changed = best_move.is_some() && best_move != Some(root_move);
+            best_move = Some(root_move);
 
             self.actual_depth = max(self.actual_depth, depth);
             self.nodes_explored.push(self.negamaxer.nodes_explored);
             self.negamaxer.nodes_explored = 0;
-            self.prev_value = entry.value;
+            self.prev_value = root_value;
             depth += self.opts.step_increment;
-            self.negamaxer.table.populate_pv(&mut self.pv, &mut s_clone, depth + 1);
+            self.negamaxer.populate_root_pv(&mut self.pv, &mut s_clone, root_move, depth + 1);
+            self.negamaxer.progress.iteration(
+                self.actual_depth,
+                self.prev_value,
+                self.negamaxer.search_nodes,
+                &self.pv,
+            );
+            if let Some(manager) = manager.as_mut() {
+                if !manager.next_iteration(&self.nodes_explored, best_move_changed) {
+                    break;
+                }
+            }
         }
         self.wall_time = start_time.elapsed();
         best_move
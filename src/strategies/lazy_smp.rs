@@ -4,17 +4,29 @@
 //! This parallel algorithm minimizes cross-thread synchronization and
 //! minimizes game state cloning, at the expense of doing more duplicative
 //! work across different threads.
+//!
+//! The threads share one transposition table. It is only lock-free when
+//! created by `LazySmp::new_packed` for a `PackableMove`; otherwise each
+//! entry is behind a mutex, which threads can contend on.
 
 extern crate num_cpus;
 extern crate rand;
 
 use super::super::interface::*;
-use super::iterative::Negamaxer;
+use super::iterative::{
+    IterativeOptions, LateMoveReductions, Line, Negamaxer, RepetitionRule, Replacement,
+};
+use super::ponder::Ponderable;
+use super::progress::SearchProgress;
+use super::stats::SearchStats;
 use super::table::*;
-use super::util::*;
+use super::time::{SearchLimits, TimeControl, TimeManager};
+use super::util::seeded_rng;
 
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use std::cmp::max;
+use std::io::{self, Read, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
 use std::thread::spawn;
@@ -24,11 +36,22 @@ use instant::{Duration, Instant};
 #[derive(Clone, Copy)]
 pub struct LazySmpOptions {
     table_byte_size: usize,
+    strategy: Replacement,
     step_increment: u8,
     max_quiescence_depth: u8,
+    delta_pruning: bool,
+    repetition_rule: RepetitionRule,
     aspiration_window: Option<Evaluation>,
+    late_move_reductions: Option<LateMoveReductions>,
+    null_move_reduction: Option<u8>,
+    killer_moves: bool,
+    history_heuristic: bool,
+    countermove_heuristic: bool,
     // Default is one per core.
     num_threads: Option<usize>,
+    seed: Option<u64>,
+    deterministic: bool,
+    multi_pv: usize,
     // TODO: optional bonus thread local TT?
     // TODO: min_TT_depth?
     // TODO: alternating depths in alternating threads
@@ -38,10 +61,21 @@ impl LazySmpOptions {
     pub fn new() -> Self {
         LazySmpOptions {
             table_byte_size: 32_000_000,
+            strategy: Replacement::TwoTier,
             step_increment: 1,
             max_quiescence_depth: 0,
+            delta_pruning: false,
+            repetition_rule: RepetitionRule::Ignore,
             aspiration_window: None,
+            late_move_reductions: None,
+            null_move_reduction: None,
+            killer_moves: true,
+            history_heuristic: true,
+            countermove_heuristic: true,
             num_threads: None,
+            seed: None,
+            deterministic: false,
+            multi_pv: 1,
         }
     }
 }
@@ -59,6 +93,13 @@ impl LazySmpOptions {
         self
     }
 
+    /// What rules to use when choosing whether to overwrite the current value
+    /// in the transposition table.
+    pub fn with_replacement_strategy(mut self, strategy: Replacement) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
     /// Increment the depth by two between iterations.
     pub fn with_double_step_increment(mut self) -> Self {
         self.step_increment = 2;
@@ -74,6 +115,25 @@ impl LazySmpOptions {
         self
     }
 
+    /// Enable [delta
+    /// pruning](https://www.chessprogramming.org/Delta_Pruning) in
+    /// quiescence search, which skips noisy moves that cannot raise the
+    /// evaluation above the best value already found. The Game must
+    /// implement `delta_pruning_margin` for this to have any effect.
+    pub fn with_delta_pruning(mut self) -> Self {
+        self.delta_pruning = true;
+        self
+    }
+
+    /// Score repeated positions as draws according to the rule. Results
+    /// that depend on the path to a position are not stored in the
+    /// transposition table, but results stored along one path may still be
+    /// reused along another that would have repeated differently.
+    pub fn with_repetition_rule(mut self, rule: RepetitionRule) -> Self {
+        self.repetition_rule = rule;
+        self
+    }
+
     pub fn with_num_threads(mut self, num_threads: usize) -> Self {
         self.num_threads = Some(num_threads);
         self
@@ -85,20 +145,137 @@ impl LazySmpOptions {
         self.aspiration_window = Some(window);
         self
     }
+
+    /// Enable late move reductions, which trade some accuracy for a much
+    /// deeper search.
+    pub fn with_late_move_reductions(mut self, lmr: LateMoveReductions) -> Self {
+        self.late_move_reductions = Some(lmr);
+        self
+    }
+
+    /// Enable [null-move
+    /// pruning](https://www.chessprogramming.org/Null_Move_Pruning), which
+    /// cuts off positions where passing the turn is already too good for the
+    /// opponent to allow, according to a search reduced by an extra
+    /// `depth_reduction` plies (R). The Game must implement `null_move` for
+    /// this to have any effect.
+    pub fn with_null_move_pruning(mut self, depth_reduction: u8) -> Self {
+        self.null_move_reduction = Some(depth_reduction);
+        self
+    }
+
+    /// Whether to first try the moves that most recently caused a cutoff at
+    /// the same depth in other branches. Also known as the killer heuristic.
+    pub fn with_killer_moves(mut self, killers: bool) -> Self {
+        self.killer_moves = killers;
+        self
+    }
+
+    /// Whether to order moves by how often they caused cutoffs anywhere in
+    /// the search tree. The Game must implement `move_index` for this to
+    /// have any effect. Each thread keeps its own history.
+    pub fn with_history_heuristic(mut self, history: bool) -> Self {
+        self.history_heuristic = history;
+        self
+    }
+
+    /// Whether to first try the move that most recently refuted the
+    /// opponent's previous move. The Game must implement `move_index` for
+    /// this to have any effect.
+    pub fn with_countermove_heuristic(mut self, countermoves: bool) -> Self {
+        self.countermove_heuristic = countermoves;
+        self
+    }
+
+    /// Seed the order in which the helper threads search the moves of the
+    /// root, which otherwise differs in every search.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Search serially, so that with a seed the same position and limits
+    /// always give the same move and principal variation, e.g. to reproduce
+    /// a bug. Limit the search by depth or nodes rather than time for this
+    /// to hold.
+    ///
+    /// This is not a parallel search: the helper threads take turns with
+    /// the main thread, each searching for as many nodes as the main thread
+    /// did in the previous iteration. It is slower than a single thread.
+    pub fn with_deterministic_search(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+
+    /// Find the best `k` moves of the root, each with its own score and
+    /// principal variation. After each iteration, the main thread searches
+    /// the root again for each move after the first, excluding the moves
+    /// already found.
+    pub fn with_multi_pv(mut self, k: usize) -> Self {
+        self.multi_pv = k;
+        self
+    }
+
+    // Options for each thread's Negamaxer.
+    fn negamax_options(&self) -> IterativeOptions {
+        IterativeOptions {
+            max_quiescence_depth: self.max_quiescence_depth,
+            delta_pruning: self.delta_pruning,
+            repetition_rule: self.repetition_rule,
+            late_move_reductions: self.late_move_reductions,
+            null_move_reduction: self.null_move_reduction,
+            killer_moves: self.killer_moves,
+            history_heuristic: self.history_heuristic,
+            countermove_heuristic: self.countermove_heuristic,
+            ..IterativeOptions::new()
+        }
+    }
 }
 
 #[derive(Clone)]
-struct Search<S: Clone> {
+struct Search<S: Clone, M: Clone> {
     state: S,
+    history: Vec<u64>,
+    root_moves: RootMoves<M>,
     depth: u8,
     timeout: Arc<AtomicBool>,
+    limits: SearchLimits,
+    // Only this helper searches, once, in deterministic mode.
+    helper: Option<usize>,
 }
 
 // A directive to the helper threads.
-enum Command<S: Clone> {
+enum Command<S: Clone, M: Clone> {
     Wait,
     Exit,
-    Search(Search<S>),
+    Search(Search<S, M>),
+}
+
+impl<S: Clone, M: Clone> Command<S, M> {
+    // Whether the helper with this id should act on the command.
+    fn is_for(&self, id: usize) -> bool {
+        match self {
+            Command::Wait => false,
+            Command::Exit => true,
+            Command::Search(search) => search.helper.is_none_or(|helper| helper == id),
+        }
+    }
+}
+
+// The command shared with the helper threads.
+type SharedCommand<G> = Arc<Mutex<Command<<G as Game>::S, <G as Game>::M>>>;
+
+// Counters of the helper threads, and how many of them are searching.
+#[derive(Default)]
+struct HelperStats {
+    searching: usize,
+    // Nodes explored by each helper.
+    nodes: Vec<u64>,
+    generate_move_calls: u64,
+    generated_moves: u64,
+    beta_cutoffs: u64,
+    first_move_cutoffs: u64,
+    table_hits: u64,
 }
 
 struct Helper<E: Evaluator>
@@ -106,9 +283,12 @@ where
     <E::G as Game>::S: Clone,
     <E::G as Game>::M: Copy + Eq,
 {
+    id: usize,
+    stats: Arc<(Mutex<HelperStats>, Condvar)>,
     negamaxer: Negamaxer<E, Arc<ConcurrentTable<<E::G as Game>::M>>>,
-    command: Arc<Mutex<Command<<E::G as Game>::S>>>,
+    command: SharedCommand<E::G>,
     waiter: Arc<Condvar>,
+    rng: StdRng,
 }
 
 impl<E: Evaluator> Helper<E>
@@ -120,36 +300,58 @@ where
         loop {
             let mut search = {
                 let command = self.command.lock().unwrap();
-                let command =
-                    self.waiter.wait_while(command, |c| matches!(*c, Command::Wait)).unwrap();
+                let mut command = self.waiter.wait_while(command, |c| !c.is_for(self.id)).unwrap();
                 match *command {
                     Command::Exit => return,
                     Command::Wait => continue,
-                    Command::Search(ref search) => search.clone(),
+                    Command::Search(ref search) if search.helper.is_some() => {
+                        // A turn of this helper alone, already counted by
+                        // the main thread.
+                        let search = search.clone();
+                        *command = Command::Wait;
+                        search
+                    }
+                    Command::Search(ref search) => {
+                        // Counted while the command is locked, so the main
+                        // thread can wait for the search to finish.
+                        self.stats.0.lock().unwrap().searching += 1;
+                        search.clone()
+                    }
                 }
             };
 
             self.negamaxer.set_timeout(search.timeout.clone());
+            self.negamaxer.set_limits(&search.limits);
+            // The helper searches the children of the root.
+            self.negamaxer.set_game_history(&search.history);
+            self.negamaxer.path.push(search.state.zobrist_hash());
             let mut alpha = WORST_EVAL;
             let mut beta = BEST_EVAL;
-            self.negamaxer.table.check(
-                search.state.zobrist_hash(),
-                search.depth,
-                &mut None,
-                &mut alpha,
-                &mut beta,
-            );
+            // The bounds of the root are only for all of its moves.
+            if search.root_moves == RootMoves::All {
+                self.negamaxer.table.check(
+                    search.state.zobrist_hash(),
+                    search.depth,
+                    0,
+                    &mut None,
+                    &mut alpha,
+                    &mut beta,
+                );
+            }
 
             // Randomize the first level of moves.
             let mut moves = Vec::new();
             E::G::generate_moves(&search.state, &mut moves);
-            moves.shuffle(&mut rand::thread_rng());
+            moves.retain(|m| search.root_moves.allows(m));
+            moves.shuffle(&mut self.rng);
             // Negamax search the rest.
             for m in moves {
                 m.apply(&mut search.state);
-                if let Some(value) =
-                    self.negamaxer.negamax(&mut search.state, search.depth, alpha, beta)
-                {
+                // Count plies from the root, for the distance of wins.
+                self.negamaxer.line.push(m);
+                let value = self.negamaxer.negamax(&mut search.state, search.depth, alpha, beta);
+                self.negamaxer.line.pop();
+                if let Some(value) = value {
                     alpha = max(alpha, -value);
                 } else {
                     break;
@@ -161,6 +363,16 @@ where
             }
 
             // Computation finished or interrupted, go back to sleep.
+            let mut stats = self.stats.0.lock().unwrap();
+            stats.searching -= 1;
+            stats.nodes[self.id] += self.negamaxer.nodes_explored;
+            stats.generate_move_calls += self.negamaxer.total_generate_move_calls;
+            stats.generated_moves += self.negamaxer.total_generated_moves;
+            stats.beta_cutoffs += self.negamaxer.beta_cutoffs;
+            stats.first_move_cutoffs += self.negamaxer.first_move_cutoffs;
+            stats.table_hits += self.negamaxer.table_hits;
+            self.negamaxer.reset_stats();
+            self.stats.1.notify_all();
         }
     }
 }
@@ -170,12 +382,13 @@ where
     <E::G as Game>::S: Clone + Zobrist,
     <E::G as Game>::M: Copy + Eq,
 {
-    max_depth: usize,
-    max_time: Duration,
+    limits: SearchLimits,
     table: Arc<ConcurrentTable<<E::G as Game>::M>>,
     negamaxer: Negamaxer<E, Arc<ConcurrentTable<<E::G as Game>::M>>>,
-    command: Arc<Mutex<Command<<E::G as Game>::S>>>,
+    command: SharedCommand<E::G>,
     signal: Arc<Condvar>,
+    helper_stats: Arc<(Mutex<HelperStats>, Condvar)>,
+    game_history: Vec<u64>,
 
     opts: LazySmpOptions,
 
@@ -186,6 +399,7 @@ where
     // Nodes explored at each depth.
     nodes_explored: Vec<u64>,
     pv: Vec<<E::G as Game>::M>,
+    lines: Vec<Line<<E::G as Game>::M>>,
     wall_time: Duration,
 }
 
@@ -206,68 +420,174 @@ where
     <E::G as Game>::M: Copy + Eq + Send,
     E: Clone + Send,
 {
+    /// Create a search whose threads lock each entry of their shared
+    /// transposition table with a mutex. See `new_packed` to avoid this.
     pub fn new(eval: E, opts: LazySmpOptions) -> LazySmp<E>
     where
         E: 'static,
     {
-        let table = Arc::new(ConcurrentTable::new(opts.table_byte_size));
+        let table = ConcurrentTable::new(opts.table_byte_size, opts.strategy);
+        Self::from_table(eval, opts, table)
+    }
+
+    /// Like `new`, but the transposition table stores moves packed by
+    /// `PackableMove`, so that it holds more positions in the same memory
+    /// and the threads share it without locks.
+    pub fn new_packed(eval: E, opts: LazySmpOptions) -> LazySmp<E>
+    where
+        E: 'static,
+        <E::G as Game>::M: PackableMove,
+    {
+        let table = ConcurrentTable::new_packed(opts.table_byte_size, opts.strategy);
+        Self::from_table(eval, opts, table)
+    }
+
+    fn from_table(
+        eval: E, opts: LazySmpOptions, table: ConcurrentTable<<E::G as Game>::M>,
+    ) -> LazySmp<E>
+    where
+        E: 'static,
+    {
+        let table = Arc::new(table);
         let command = Arc::new(Mutex::new(Command::Wait));
         let signal = Arc::new(Condvar::new());
+        let num_helpers = opts.num_threads.unwrap_or_else(num_cpus::get).max(1) - 1;
+        let helper_stats = Arc::new((
+            Mutex::new(HelperStats { nodes: vec![0; num_helpers], ..HelperStats::default() }),
+            Condvar::new(),
+        ));
         // start n-1 helper threads
-        for _ in 1..opts.num_threads.unwrap_or_else(num_cpus::get) {
+        for id in 0..num_helpers {
             let table2 = table.clone();
             let eval2 = eval.clone();
             let command2 = command.clone();
             let waiter = signal.clone();
+            let stats = helper_stats.clone();
             spawn(move || {
                 let mut helper = Helper {
-                    negamaxer: Negamaxer::new(table2, eval2, opts.max_quiescence_depth, true),
+                    id,
+                    stats,
+                    negamaxer: Negamaxer::new(table2, eval2, opts.negamax_options()),
                     command: command2,
                     waiter,
+                    rng: seeded_rng(opts.seed, id + 1),
                 };
                 helper.process();
             });
         }
-        let negamaxer = Negamaxer::new(table.clone(), eval, opts.max_quiescence_depth, true);
+        let negamaxer = Negamaxer::new(table.clone(), eval, opts.negamax_options());
         LazySmp {
-            max_depth: 100,
-            max_time: Duration::from_secs(5),
+            limits: SearchLimits::new().with_time(Duration::from_secs(5)),
             table,
             negamaxer,
             command,
             signal,
+            helper_stats,
+            game_history: Vec::new(),
             prev_value: 0,
             opts,
             actual_depth: 0,
             nodes_explored: Vec::new(),
             pv: Vec::new(),
+            lines: Vec::new(),
             wall_time: Duration::default(),
         }
     }
 
-    /// Set the maximum depth to search. Disables the timeout.
+    /// Set the maximum depth to search. Disables the timeout, see
+    /// `set_limits` to combine them.
     /// This can be changed between moves while reusing the transposition table.
     pub fn set_max_depth(&mut self, depth: usize) {
-        self.max_depth = depth;
-        self.max_time = Duration::new(0, 0);
+        self.limits = SearchLimits::new().with_depth(depth);
     }
 
     /// Set the maximum time to compute the best move. When the timeout is
     /// hit, it returns the best move found of the previous full
     /// iteration. Unlimited max depth.
     pub fn set_timeout(&mut self, max_time: Duration) {
-        self.max_time = max_time;
-        self.max_depth = 100;
+        self.limits = SearchLimits::new().with_time(max_time);
+    }
+
+    /// Budget the time for each move from the state of the game clock,
+    /// which should be updated before each move. Unlimited max depth.
+    pub fn set_time_control(&mut self, clock: TimeControl) {
+        self.limits = SearchLimits::new().with_time_control(clock);
+    }
+
+    /// Set any combination of limits, replacing those set before.
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    /// Call the callback with the progress of the search after each
+    /// completed iteration, and during iterations that take longer than
+    /// `interval`.
+    pub fn set_progress_callback<F>(&mut self, interval: Duration, callback: F)
+    where
+        F: FnMut(&SearchProgress<<E::G as Game>::M>) + Send + 'static,
+    {
+        self.negamaxer.progress.set_callback(interval, Box::new(callback));
+    }
+
+    /// Set the Zobrist hashes of the positions played before the state
+    /// passed to the next `choose_move`, oldest first, so that the
+    /// repetition rule can count them.
+    pub fn set_game_history(&mut self, history: &[u64]) {
+        self.game_history = history.to_vec();
+        self.negamaxer.set_game_history(history);
     }
 
-    // TODO: gather stats from helper threads.
-    // Return a human-readable summary of the last move generation.
-    //pub fn stats(&self) -> String {
-    //}
+    /// Restrict the moves of the root that the following searches may
+    /// choose from, until replaced with `RootMoves::All`. The helper threads
+    /// only search these moves too.
+    pub fn set_root_moves(&mut self, root_moves: RootMoves<<E::G as Game>::M>) {
+        self.negamaxer.root_moves = root_moves;
+    }
+
+    /// Return statistics about the last move generation, including the
+    /// helper threads.
+    pub fn stats(&self) -> SearchStats {
+        let mut stats =
+            self.negamaxer.stats(self.actual_depth, &self.nodes_explored, self.wall_time);
+        let helpers = self.helper_stats.0.lock().unwrap();
+        let main_nodes = stats.nodes_per_depth.iter().sum::<u64>() + stats.partial_nodes;
+        stats.thread_nodes.push(main_nodes);
+        stats.thread_nodes.extend_from_slice(&helpers.nodes);
+        stats.generate_move_calls += helpers.generate_move_calls;
+        stats.generated_moves += helpers.generated_moves;
+        stats.beta_cutoffs += helpers.beta_cutoffs;
+        stats.first_move_cutoffs += helpers.first_move_cutoffs;
+        stats.table_hits += helpers.table_hits;
+        stats
+    }
+
+    /// Save the transposition table, so that a later search can resume the
+    /// analysis after `read_table`.
+    pub fn write_table<W: Write>(&self, w: &mut W) -> io::Result<()>
+    where
+        <E::G as Game>::M: SerializableMove,
+    {
+        self.table.write_to(w)
+    }
+
+    /// Load a table saved by `write_table`, possibly with a different table
+    /// size. Entries already in the table are kept where there's room.
+    pub fn read_table<R: Read>(&mut self, r: &mut R) -> io::Result<()>
+    where
+        <E::G as Game>::M: SerializableMove,
+    {
+        self.table.read_from(r)
+    }
 
     #[doc(hidden)]
     pub fn root_value(&self) -> Evaluation {
-        unclamp_value(self.prev_value)
+        self.prev_value
+    }
+
+    /// The result of the last search for the player to move: a forced win
+    /// or loss, or else the evaluation of the root.
+    pub fn root_score(&self) -> Score {
+        Score::from(self.prev_value)
     }
 
     /// Return what the engine considered to be the best sequence of moves
@@ -275,6 +595,16 @@ where
     pub fn principal_variation(&self) -> &[<E::G as Game>::M] {
         &self.pv[..]
     }
+
+    /// Return the best moves of the last search, up to the count set by
+    /// `with_multi_pv`, each with its score and principal variation, best
+    /// first.
+    pub fn principal_variations(&self) -> Vec<Line<<E::G as Game>::M>> {
+        if !self.lines.is_empty() {
+            return self.lines.clone();
+        }
+        self.pv.first().map(|&m| (m, self.root_score(), self.pv.clone())).into_iter().collect()
+    }
 }
 
 impl<E: Evaluator> Strategy<E::G> for LazySmp<E>
@@ -283,24 +613,65 @@ where
     <E::G as Game>::M: Copy + Eq,
 {
     fn choose_move(&mut self, s: &<E::G as Game>::S) -> Option<<E::G as Game>::M> {
+        let (timeout, manager) = self.limits.start_timer();
+        self.search(s, timeout, manager)
+    }
+}
+
+impl<E: Evaluator> Ponderable<E::G> for LazySmp<E>
+where
+    <E::G as Game>::S: Clone + Zobrist,
+    <E::G as Game>::M: Copy + Eq,
+{
+    fn choose_move_until(
+        &mut self, s: &<E::G as Game>::S, stop: Arc<AtomicBool>,
+    ) -> Option<<E::G as Game>::M> {
+        self.search(s, stop, None)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.limits.expected_time()
+    }
+
+    fn principal_variation(&self) -> &[<E::G as Game>::M] {
+        &self.pv[..]
+    }
+}
+
+impl<E: Evaluator> LazySmp<E>
+where
+    <E::G as Game>::S: Clone + Zobrist,
+    <E::G as Game>::M: Copy + Eq,
+{
+    // Iteratively deepen until the maximum depth, the timeout signal, or the
+    // time manager decides to stop.
+    fn search(
+        &mut self, s: &<E::G as Game>::S, timeout: Arc<AtomicBool>,
+        mut manager: Option<TimeManager>,
+    ) -> Option<<E::G as Game>::M> {
         self.table.concurrent_advance_generation();
-        // Reset stats.
+        self.negamaxer.ordering.new_search();
+        // Reset stats. The helpers are idle between searches.
         self.nodes_explored.clear();
+        self.negamaxer.reset_stats();
+        {
+            let mut helpers = self.helper_stats.0.lock().unwrap();
+            let num_helpers = helpers.nodes.len();
+            *helpers = HelperStats { nodes: vec![0; num_helpers], ..HelperStats::default() };
+        }
         self.actual_depth = 0;
+        self.lines.clear();
         let start_time = Instant::now();
-        // Start timer if configured.
-        self.negamaxer.set_timeout(if self.max_time == Duration::new(0, 0) {
-            Arc::new(AtomicBool::new(false))
-        } else {
-            timeout_signal(self.max_time)
-        });
-
-        let root_hash = s.zobrist_hash();
+        self.negamaxer.set_timeout(timeout.clone());
+        self.negamaxer.set_limits(&self.limits);
+        self.negamaxer.progress.start();
+
         let mut s_clone = s.clone();
         let mut best_move = None;
 
-        let mut depth = self.max_depth as u8 % self.opts.step_increment;
-        while depth <= self.max_depth as u8 {
+        let max_depth = self.limits.max_depth() as u8;
+        let mut depth = max_depth % self.opts.step_increment;
+        while depth <= max_depth {
             // First, a serial aspiration search to at least establish some bounds.
             if self
                 .negamaxer
@@ -317,12 +688,18 @@ where
             }
 
             let iteration_done = Arc::new(AtomicBool::new(false));
-            {
+            if self.opts.deterministic {
+                self.helper_turns(s, depth, &timeout);
+            } else {
                 let mut command = self.command.lock().unwrap();
                 *command = Command::Search(Search {
                     state: s.clone(),
+                    history: self.game_history.clone(),
+                    root_moves: self.negamaxer.root_moves.clone(),
                     depth,
                     timeout: iteration_done.clone(),
+                    limits: SearchLimits::new(),
+                    helper: None,
                 });
                 self.signal.notify_all();
             }
@@ -332,20 +709,76 @@ where
                 *self.command.lock().unwrap() = Command::Wait;
             }
             iteration_done.store(true, Ordering::Relaxed);
-            if value.is_none() {
+            let value = match value {
+                Some(value) => value,
                 // Timeout. Return the best move from the previous depth.
-                break;
+                None => break,
+            };
+            let (root_move, root_value) = match self.negamaxer.root_result(value) {
+                Some(result) => result,
+                None => break,
+            };
+            if self.opts.multi_pv > 1 {
+                let best = (root_move, root_value);
+                match self.negamaxer.best_lines(&mut s_clone, depth + 1, self.opts.multi_pv, best) {
+                    Some(lines) => self.lines = lines,
+                    None => break,
+                }
             }
 
-            let entry = self.table.lookup(root_hash).unwrap();
-            best_move = entry.best_move;
+            let best_move_changed = best_move.is_some() && best_move != Some(root_move);
+            best_move = Some(root_move);
 
             self.actual_depth = max(self.actual_depth, depth);
-            self.prev_value = entry.value;
+            // Only counts the nodes of the main thread.
+            self.nodes_explored.push(self.negamaxer.nodes_explored);
+            self.negamaxer.nodes_explored = 0;
+            self.prev_value = root_value;
             depth += self.opts.step_increment;
-            self.table.populate_pv(&mut self.pv, &mut s_clone, depth + 1);
+            self.negamaxer.populate_root_pv(&mut self.pv, &mut s_clone, root_move, depth + 1);
+            self.negamaxer.progress.iteration(
+                self.actual_depth,
+                self.prev_value,
+                self.negamaxer.search_nodes,
+                &self.pv,
+            );
+            if let Some(manager) = manager.as_mut() {
+                if !manager.next_iteration(&self.nodes_explored, best_move_changed) {
+                    break;
+                }
+            }
         }
+        // Wait for the helpers to stop, so that their stats are complete.
+        let (helpers, idle) = &*self.helper_stats;
+        drop(idle.wait_while(helpers.lock().unwrap(), |h| h.searching > 0).unwrap());
         self.wall_time = start_time.elapsed();
         best_move
     }
+
+    // Let each helper search the iteration in turn, with a node budget, so
+    // that they fill the table the same way every time.
+    fn helper_turns(&self, s: &<E::G as Game>::S, depth: u8, timeout: &Arc<AtomicBool>) {
+        let node_limit = match self.nodes_explored.last() {
+            Some(&nodes) => nodes,
+            None => return,
+        };
+        let (helpers, idle) = &*self.helper_stats;
+        let num_helpers = helpers.lock().unwrap().nodes.len();
+        for id in 0..num_helpers {
+            helpers.lock().unwrap().searching += 1;
+            *self.command.lock().unwrap() = Command::Search(Search {
+                state: s.clone(),
+                history: self.game_history.clone(),
+                root_moves: self.negamaxer.root_moves.clone(),
+                depth,
+                timeout: timeout.clone(),
+                limits: SearchLimits::new()
+                    .with_nodes(node_limit)
+                    .with_stop_token(self.limits.stop_token()),
+                helper: Some(id),
+            });
+            self.signal.notify_all();
+            drop(idle.wait_while(helpers.lock().unwrap(), |h| h.searching > 0).unwrap());
+        }
+    }
 }
@@ -0,0 +1,380 @@
+//! A strategy that uses Monte Carlo Tree Search.
+//!
+//! Rather than evaluating positions with an `Evaluator`, this plays out many
+//! random games from the current state, and builds a tree of statistics
+//! about which moves led to wins. The tree is grown towards promising moves
+//! using the UCT (Upper Confidence bounds applied to Trees) formula. This
+//! works with any `Game`, and is especially useful for games with high
+//! branching factors or without a decent evaluation function.
+//!
+//! The search can opt into multiple threads, either sharing a single tree and
+//! using virtual loss to spread the threads across different branches, or
+//! growing independent trees that are merged at the end (root parallelism).
+
+extern crate rand;
+
+use super::super::interface::*;
+use super::super::util::*;
+use super::util::*;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use instant::{Duration, Instant};
+
+/// Options to use for the Monte Carlo Tree Search engine.
+#[derive(Clone, Copy)]
+pub struct MctsOptions {
+    exploration_constant: f32,
+    max_rollout_depth: u32,
+    num_threads: usize,
+    root_parallelism: bool,
+    seed: Option<u64>,
+}
+
+impl MctsOptions {
+    pub fn new() -> Self {
+        MctsOptions {
+            exploration_constant: std::f32::consts::SQRT_2,
+            max_rollout_depth: 100,
+            num_threads: 1,
+            root_parallelism: false,
+            seed: None,
+        }
+    }
+}
+
+impl Default for MctsOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MctsOptions {
+    /// How much to favor exploring rarely visited moves over exploiting
+    /// moves that have done well so far. Defaults to the theoretical optimum
+    /// of sqrt(2).
+    pub fn with_exploration_constant(mut self, constant: f32) -> Self {
+        self.exploration_constant = constant;
+        self
+    }
+
+    /// Maximum number of moves to play in a single random rollout. Rollouts
+    /// that hit this limit without the game ending are scored as a draw.
+    pub fn with_max_rollout_depth(mut self, depth: u32) -> Self {
+        self.max_rollout_depth = depth;
+        self
+    }
+
+    /// Number of threads to run rollouts on, all sharing one tree unless
+    /// `with_root_parallelism` is set. Defaults to 1.
+    pub fn with_num_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads;
+        self
+    }
+
+    /// Have each thread grow its own independent tree, and sum up the visits
+    /// of each root move at the end, instead of sharing a single tree. This
+    /// avoids contention between threads at the cost of less informed
+    /// trees.
+    pub fn with_root_parallelism(mut self) -> Self {
+        self.root_parallelism = true;
+        self
+    }
+
+    /// Seed the random rollouts. With the default single thread and a limit
+    /// on rollouts rather than time, the same seed plays the same moves.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+struct Node<M> {
+    // The move that leads from the parent to this node. Only None at the root.
+    m: Option<M>,
+    // Number of rollouts through this node, including those in progress.
+    visits: AtomicU32,
+    // Sum of rollout results from the perspective of the player who made the
+    // move into this node: 2 for a win, 1 for a draw, 0 for a loss.
+    //
+    // Visits are counted when a thread descends through the node, but the
+    // score is only added once the rollout finishes. In the meantime, the
+    // rollout counts as a loss, which is a "virtual loss" that steers other
+    // threads towards different branches.
+    score: AtomicU32,
+    // Unset until the node is expanded.
+    children: OnceLock<Vec<Node<M>>>,
+}
+
+impl<M> Node<M> {
+    fn new(m: Option<M>) -> Self {
+        Node { m, visits: AtomicU32::new(0), score: AtomicU32::new(0), children: OnceLock::new() }
+    }
+
+    // Record the start of a rollout through this node, applying a virtual
+    // loss. Returns the previous number of visits.
+    fn visit(&self) -> u32 {
+        self.visits.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // Record a rollout result, from the perspective of the player who made
+    // the move into this node.
+    fn update(&self, result: i32) {
+        self.score.fetch_add((1 + result) as u32, Ordering::Relaxed);
+    }
+
+    // Upper confidence bound of this node's value, as seen from its parent.
+    fn uct_value(&self, parent_visits: u32, exploration_constant: f32) -> f32 {
+        let visits = self.visits.load(Ordering::Relaxed);
+        if visits == 0 {
+            // Always try unexplored moves first.
+            return f32::INFINITY;
+        }
+        let visits = visits as f32;
+        let mean = self.score.load(Ordering::Relaxed) as f32 / (2.0 * visits);
+        mean + exploration_constant * ((parent_visits as f32).ln() / visits).sqrt()
+    }
+}
+
+// Convert the game result at a terminal state to a rollout result from the
+// perspective of the player to move.
+fn winner_result(winner: Winner) -> i32 {
+    winner.evaluate().signum()
+}
+
+// Create a child node for each move from this state.
+fn expand<G: Game>(state: &G::S) -> Vec<Node<G::M>> {
+    let mut moves = Vec::new();
+    G::generate_moves(state, &mut moves);
+    moves.into_iter().map(|m| Node::new(Some(m))).collect()
+}
+
+// The per-thread search state.
+struct Worker<G: Game> {
+    opts: MctsOptions,
+    timeout: Arc<AtomicBool>,
+    move_pool: MovePool<G::M>,
+    rng: StdRng,
+}
+
+impl<G: Game> Worker<G>
+where
+    G::M: Copy,
+{
+    fn new(opts: MctsOptions, timeout: Arc<AtomicBool>, rng: StdRng) -> Self {
+        Worker { opts, timeout, move_pool: MovePool::default(), rng }
+    }
+
+    // Run simulations until the timeout or the shared rollout budget is hit.
+    // Returns the number of rollouts this worker completed.
+    fn search(
+        &mut self, root: &Node<G::M>, state: &mut G::S, rollouts: &AtomicU32, max_rollouts: u32,
+    ) -> u32 {
+        let mut count = 0;
+        while !self.timeout.load(Ordering::Relaxed)
+            && rollouts.fetch_add(1, Ordering::Relaxed) < max_rollouts
+        {
+            self.simulate(root, state);
+            count += 1;
+        }
+        count
+    }
+
+    // Play random moves until the game ends or the depth limit is hit.
+    // Returns the result from the perspective of the player to move at `state`.
+    fn rollout(&mut self, state: &mut G::S) -> i32 {
+        let mut played = self.move_pool.alloc();
+        let mut moves = self.move_pool.alloc();
+        // Flips between the perspective of the initial player and their opponent.
+        let mut sign = 1;
+        let result = loop {
+            if let Some(winner) = G::get_winner(state) {
+                break sign * winner_result(winner);
+            }
+            if played.len() as u32 >= self.opts.max_rollout_depth {
+                break 0;
+            }
+            moves.clear();
+            G::generate_moves(state, &mut moves);
+            let m = match moves.choose(&mut self.rng) {
+                Some(m) => *m,
+                // No moves is a loss, as in the other strategies.
+                None => break -sign,
+            };
+            m.apply(state);
+            played.push(m);
+            sign = -sign;
+        };
+        // Restore state.
+        for m in played.iter().rev() {
+            m.undo(state);
+        }
+        self.move_pool.free(played);
+        self.move_pool.free(moves);
+        result
+    }
+
+    // Select down the tree, expand a leaf, and roll it out. Updates the
+    // statistics along the path. Returns the result from the perspective of
+    // the player to move at `state`.
+    fn simulate(&mut self, node: &Node<G::M>, state: &mut G::S) -> i32 {
+        let prev_visits = node.visit();
+        let result = if let Some(winner) = G::get_winner(state) {
+            winner_result(winner)
+        } else if node.children.get().is_none() && prev_visits == 0 {
+            // Fresh leaf, play it out.
+            self.rollout(state)
+        } else {
+            let exploration_constant = self.opts.exploration_constant;
+            let children = node.children.get_or_init(|| expand::<G>(state));
+            let parent_visits = node.visits.load(Ordering::Relaxed);
+            let mut best = None;
+            let mut best_uct = f32::NEG_INFINITY;
+            for child in children.iter() {
+                let uct = child.uct_value(parent_visits, exploration_constant);
+                if uct > best_uct {
+                    best_uct = uct;
+                    best = Some(child);
+                }
+            }
+            match best {
+                Some(child) => {
+                    let m = child.m.unwrap();
+                    m.apply(state);
+                    let result = -self.simulate(child, state);
+                    m.undo(state);
+                    result
+                }
+                // No moves is a loss, as in the other strategies.
+                None => -1,
+            }
+        };
+        // Stats are stored from the perspective of the player who just moved.
+        node.update(-result);
+        result
+    }
+}
+
+pub struct MonteCarloTreeSearch<G: Game> {
+    max_rollouts: u32,
+    max_time: Duration,
+    opts: MctsOptions,
+    // Seeds the workers of each search.
+    rng: StdRng,
+
+    // Runtime stats for the last move generated.
+    rollouts: u32,
+    wall_time: Duration,
+
+    game_type: PhantomData<G>,
+}
+
+impl<G: Game> MonteCarloTreeSearch<G> {
+    pub fn new(opts: MctsOptions) -> Self {
+        MonteCarloTreeSearch {
+            max_rollouts: u32::MAX,
+            max_time: Duration::from_secs(5),
+            opts,
+            rng: seeded_rng(opts.seed, 0),
+            rollouts: 0,
+            wall_time: Duration::default(),
+            game_type: PhantomData,
+        }
+    }
+
+    /// Set the maximum number of rollouts to play out. Disables the timeout.
+    pub fn set_max_rollouts(&mut self, rollouts: u32) {
+        self.max_rollouts = rollouts;
+        self.max_time = Duration::new(0, 0);
+    }
+
+    /// Set the maximum time to compute the best move. When the timeout is
+    /// hit, it returns the most visited move so far. Unlimited rollouts.
+    pub fn set_timeout(&mut self, max_time: Duration) {
+        self.max_time = max_time;
+        self.max_rollouts = u32::MAX;
+    }
+
+    /// Return a human-readable summary of the last move generation.
+    pub fn stats(&self) -> String {
+        let throughput = self.rollouts as f64 / self.wall_time.as_secs_f64();
+        format!("Played {} rollouts.\n{} rollouts/sec", self.rollouts, throughput as usize)
+    }
+}
+
+impl<G: Game> Strategy<G> for MonteCarloTreeSearch<G>
+where
+    G::S: Clone + Send,
+    G::M: Copy + Send + Sync,
+{
+    fn choose_move(&mut self, s: &G::S) -> Option<G::M> {
+        let start_time = Instant::now();
+        // Start timer if configured.
+        let timeout = if self.max_time == Duration::new(0, 0) {
+            Arc::new(AtomicBool::new(false))
+        } else {
+            timeout_signal(self.max_time)
+        };
+
+        let num_threads = self.opts.num_threads.max(1);
+        let num_trees = if self.opts.root_parallelism { num_threads } else { 1 };
+        let mut roots = Vec::with_capacity(num_trees);
+        for _ in 0..num_trees {
+            // Always expand the root, so there is a move to return.
+            let children = expand::<G>(s);
+            if children.is_empty() {
+                return None;
+            }
+            let root = Node::new(None);
+            let _ = root.children.set(children);
+            roots.push(root);
+        }
+
+        let rollouts = AtomicU32::new(0);
+        let max_rollouts = self.max_rollouts;
+        let seed = self.rng.gen::<u64>();
+        let opts = self.opts;
+        let roots = &roots;
+        let rollouts = &rollouts;
+        self.rollouts =
+            thread::scope(|scope| {
+                // Start n-1 helper threads, and do the rest of the work on this one.
+                let helpers = (1..num_threads)
+                    .map(|i| {
+                        let timeout = timeout.clone();
+                        let mut state = s.clone();
+                        scope.spawn(move || {
+                            Worker::<G>::new(opts, timeout, seeded_rng(Some(seed), i)).search(
+                                &roots[i % num_trees],
+                                &mut state,
+                                rollouts,
+                                max_rollouts,
+                            )
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let mut state = s.clone();
+                let count = Worker::<G>::new(opts, timeout.clone(), seeded_rng(Some(seed), 0))
+                    .search(&roots[0], &mut state, rollouts, max_rollouts);
+                count + helpers.into_iter().map(|helper| helper.join().unwrap()).sum::<u32>()
+            });
+        self.wall_time = start_time.elapsed();
+
+        // Pick the most visited move, which is more robust than the highest
+        // mean score. The roots all have the same moves in the same order, so
+        // merge the independent trees by index.
+        let mut visits = vec![0u64; roots[0].children.get().unwrap().len()];
+        for root in roots.iter() {
+            for (total, child) in visits.iter_mut().zip(root.children.get().unwrap().iter()) {
+                *total += child.visits.load(Ordering::Relaxed) as u64;
+            }
+        }
+        let best = (0..visits.len()).max_by_key(|&i| visits[i])?;
+        roots[0].children.get().unwrap()[best].m
+    }
+}
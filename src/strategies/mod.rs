@@ -1,10 +1,21 @@
 //! Strategy implementations.
 
+pub mod book;
+pub mod expectiminimax;
 pub mod iterative;
 pub mod lazy_smp;
+pub mod mcts;
+pub mod mtdf;
 pub mod negamax;
+pub mod pns;
+pub mod ponder;
+pub mod progress;
 pub mod random;
+pub mod skill;
+pub mod stats;
+pub mod time;
 pub mod ybw;
 
+mod ordering;
 mod table;
 mod util;
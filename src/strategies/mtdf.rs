@@ -0,0 +1,243 @@
+//! An implementation of the MTD(f) search algorithm.
+//!
+//! Instead of searching each iteration with a full window, MTD(f) runs a
+//! sequence of zero-window searches that converge on the minimax value,
+//! starting from the value of the previous iteration. Each zero-window search
+//! only proves whether the value is above or below a single bound, and the
+//! transposition table carries the results from one search to the next.
+
+use super::super::interface::*;
+use super::iterative::{IterativeOptions, Negamaxer, TranspositionTable};
+use super::stats::SearchStats;
+use super::table::*;
+use super::time::{SearchLimits, TimeControl};
+
+use std::cmp::max;
+use std::io::{self, Read, Write};
+use instant::{Duration, Instant};
+
+pub struct MtdfSearch<E: Evaluator> {
+    limits: SearchLimits,
+    negamaxer: Negamaxer<E, TranspositionTable<<E::G as Game>::M>>,
+    prev_value: Evaluation,
+    opts: IterativeOptions,
+
+    // Runtime stats for the last move generated.
+
+    // Maximum depth used to produce the move.
+    actual_depth: u8,
+    // Nodes explored at each depth.
+    nodes_explored: Vec<u64>,
+    // Number of zero-window searches run at each depth.
+    passes: Vec<u32>,
+    pv: Vec<<E::G as Game>::M>,
+    wall_time: Duration,
+}
+
+impl<E: Evaluator> MtdfSearch<E>
+where
+    <E::G as Game>::M: Copy + Eq,
+    <E::G as Game>::S: Clone + Zobrist,
+{
+    /// Create a new MTD(f) search. The options are shared with
+    /// `IterativeSearch`, though the null window and aspiration window
+    /// settings have no effect, as every search is already zero-window.
+    pub fn new(eval: E, opts: IterativeOptions) -> MtdfSearch<E> {
+        let table = TranspositionTable::new(opts.table_byte_size, opts.strategy);
+        Self::from_table(eval, opts, table)
+    }
+
+    /// Like `new`, but the transposition table stores moves packed by
+    /// `PackableMove`, so that it holds more positions in the same memory.
+    pub fn new_packed(eval: E, opts: IterativeOptions) -> MtdfSearch<E>
+    where
+        <E::G as Game>::M: PackableMove,
+    {
+        let table = TranspositionTable::new_packed(opts.table_byte_size, opts.strategy);
+        Self::from_table(eval, opts, table)
+    }
+
+    fn from_table(
+        eval: E, opts: IterativeOptions, table: TranspositionTable<<E::G as Game>::M>,
+    ) -> MtdfSearch<E> {
+        let negamaxer = Negamaxer::new(table, eval, opts.with_null_window_search(false));
+        MtdfSearch {
+            limits: SearchLimits::new().with_time(Duration::from_secs(5)),
+            prev_value: 0,
+            negamaxer,
+            opts,
+            actual_depth: 0,
+            nodes_explored: Vec::new(),
+            passes: Vec::new(),
+            pv: Vec::new(),
+            wall_time: Duration::default(),
+        }
+    }
+
+    /// Set the maximum depth to search. Disables the timeout, see
+    /// `set_limits` to combine them.
+    /// This can be changed between moves while reusing the transposition table.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.limits = SearchLimits::new().with_depth(depth);
+    }
+
+    /// Set the maximum time to compute the best move. When the timeout is
+    /// hit, it returns the best move found of the previous full
+    /// iteration. Unlimited max depth.
+    pub fn set_timeout(&mut self, max_time: Duration) {
+        self.limits = SearchLimits::new().with_time(max_time);
+    }
+
+    /// Budget the time for each move from the state of the game clock,
+    /// which should be updated before each move. Unlimited max depth.
+    pub fn set_time_control(&mut self, clock: TimeControl) {
+        self.limits = SearchLimits::new().with_time_control(clock);
+    }
+
+    /// Set any combination of limits, replacing those set before.
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    /// Set the Zobrist hashes of the positions played before the state
+    /// passed to the next `choose_move`, oldest first, so that the
+    /// repetition rule can count them.
+    pub fn set_game_history(&mut self, history: &[u64]) {
+        self.negamaxer.set_game_history(history);
+    }
+
+    /// Return statistics about the last move generation.
+    pub fn stats(&self) -> SearchStats {
+        SearchStats {
+            zero_window_searches: Some(self.passes.iter().sum()),
+            ..self.negamaxer.stats(self.actual_depth, &self.nodes_explored, self.wall_time)
+        }
+    }
+
+    /// Save the transposition table, so that a later search can resume the
+    /// analysis after `read_table`.
+    pub fn write_table<W: Write>(&self, w: &mut W) -> io::Result<()>
+    where
+        <E::G as Game>::M: SerializableMove,
+    {
+        self.negamaxer.table.write_to(w)
+    }
+
+    /// Load a table saved by `write_table`, possibly with a different table
+    /// size. Entries already in the table are kept where there's room.
+    pub fn read_table<R: Read>(&mut self, r: &mut R) -> io::Result<()>
+    where
+        <E::G as Game>::M: SerializableMove,
+    {
+        self.negamaxer.table.read_from(r)
+    }
+
+    #[doc(hidden)]
+    pub fn root_value(&self) -> Evaluation {
+        self.prev_value
+    }
+
+    /// The result of the last search for the player to move: a forced win
+    /// or loss, or else the evaluation of the root.
+    pub fn root_score(&self) -> Score {
+        Score::from(self.prev_value)
+    }
+
+    /// Return what the engine considered to be the best sequence of moves
+    /// from both sides.
+    pub fn principal_variation(&self) -> &[<E::G as Game>::M] {
+        &self.pv[..]
+    }
+
+    // Converge on the value of the root with a series of zero-window
+    // searches, starting from the guess. Returns None if it hits the timeout.
+    fn mtdf(
+        &mut self, s: &mut <E::G as Game>::S, depth: u8, mut guess: Evaluation,
+    ) -> Option<(Evaluation, Option<<E::G as Game>::M>)> {
+        let root_hash = s.zobrist_hash();
+        let mut lower = WORST_EVAL;
+        let mut upper = BEST_EVAL;
+        // Only moves from searches that failed high are proven to reach the
+        // lower bound.
+        let mut best_move = None;
+        let mut passes = 0;
+        while lower < upper {
+            let beta = if guess == lower { guess + 1 } else { guess };
+            guess = self.negamaxer.negamax(s, depth, beta - 1, beta)?;
+            passes += 1;
+            // The root may not be kept in the table, so take its move from
+            // the search.
+            let root_move = self.negamaxer.root_result(guess).map(|(m, _)| m);
+            if guess < beta {
+                upper = guess;
+            } else {
+                lower = guess;
+                best_move = root_move;
+            }
+            if best_move.is_none() {
+                // Every search so far failed low, so any move is as good as any other.
+                best_move = root_move;
+            }
+        }
+        self.passes.push(passes);
+        if let Some(m) = best_move {
+            // The last search may have failed low and left an arbitrary move
+            // in the root entry. Store the converged result so the principal
+            // variation starts with the proven best move.
+            self.negamaxer.table.store(root_hash, guess, depth, EntryFlag::Exact, m);
+        }
+        Some((guess, best_move))
+    }
+}
+
+impl<E: Evaluator> Strategy<E::G> for MtdfSearch<E>
+where
+    <E::G as Game>::S: Clone + Zobrist,
+    <E::G as Game>::M: Copy + Eq,
+{
+    fn choose_move(&mut self, s: &<E::G as Game>::S) -> Option<<E::G as Game>::M> {
+        self.negamaxer.table.advance_generation();
+        self.negamaxer.ordering.new_search();
+        // Reset stats.
+        self.nodes_explored.clear();
+        self.passes.clear();
+        self.negamaxer.reset_stats();
+        self.actual_depth = 0;
+        let start_time = Instant::now();
+        // Start timer if configured.
+        let (timeout, mut manager) = self.limits.start_timer();
+        self.negamaxer.set_timeout(timeout);
+        self.negamaxer.set_limits(&self.limits);
+
+        let mut s_clone = s.clone();
+        let mut best_move = None;
+
+        let max_depth = self.limits.max_depth() as u8;
+        let mut depth = max_depth % self.opts.step_increment;
+        while depth <= max_depth {
+            let (value, m) = match self.mtdf(&mut s_clone, depth + 1, self.prev_value) {
+                Some(result) => result,
+                // Timeout. Return the best move from the previous depth.
+                None => break,
+            };
+            let best_move_changed = best_move.is_some() && best_move != m;
+            best_move = m;
+
+            self.actual_depth = max(self.actual_depth, depth);
+            self.nodes_explored.push(self.negamaxer.nodes_explored);
+            self.negamaxer.nodes_explored = 0;
+            self.prev_value = value;
+            depth += self.opts.step_increment;
+            if let Some(m) = m {
+                self.negamaxer.populate_root_pv(&mut self.pv, &mut s_clone, m, depth + 1);
+            }
+            if let Some(manager) = manager.as_mut() {
+                if !manager.next_iteration(&self.nodes_explored, best_move_changed) {
+                    break;
+                }
+            }
+        }
+        self.wall_time = start_time.elapsed();
+        best_move
+    }
+}
@@ -1,19 +1,27 @@
 //! An implementation of Negamax.
 //!
 //! With only the basic alpha-pruning implemented. This picks randomly among
-//! the "best" moves, so that it's non-deterministic.
+//! the "best" moves, so that it's non-deterministic unless seeded.
 
 use super::super::interface::*;
 use super::super::util::*;
-use super::util::*;
+use super::util::seeded_rng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use std::cmp::max;
 
+// Whether the root may choose a move.
+type RootFilter<M> = fn(&RootMoves<M>, &M) -> bool;
+
 pub struct Negamax<E: Evaluator> {
     max_depth: usize,
     move_pool: MovePool<<E::G as Game>::M>,
-    rng: rand::rngs::ThreadRng,
+    rng: StdRng,
     prev_value: Evaluation,
+    root_moves: RootMoves<<E::G as Game>::M>,
+    // Compares moves for root_moves, which is only set where moves can be
+    // compared.
+    root_filter: RootFilter<<E::G as Game>::M>,
     eval: E,
 }
 
@@ -22,15 +30,33 @@ impl<E: Evaluator> Negamax<E> {
         Negamax {
             max_depth: depth,
             move_pool: MovePool::<_>::default(),
-            rng: rand::thread_rng(),
+            rng: seeded_rng(None, 0),
             prev_value: 0,
+            root_moves: RootMoves::All,
+            root_filter: |_, _| true,
             eval,
         }
     }
 
+    /// Seed the choice among the best moves, so that the same seed plays
+    /// the same moves.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = seeded_rng(Some(seed), 0);
+    }
+
+    /// Restrict the moves of the root that the following searches may
+    /// choose from, until replaced with `RootMoves::All`.
+    pub fn set_root_moves(&mut self, root_moves: RootMoves<<E::G as Game>::M>)
+    where
+        <E::G as Game>::M: PartialEq,
+    {
+        self.root_moves = root_moves;
+        self.root_filter = RootMoves::allows;
+    }
+
     #[doc(hidden)]
     pub fn root_value(&self) -> Evaluation {
-        unclamp_value(self.prev_value)
+        self.prev_value
     }
 
     fn negamax(
@@ -39,15 +65,17 @@ impl<E: Evaluator> Negamax<E> {
     where
         <<E as Evaluator>::G as Game>::M: Copy,
     {
+        // The children of the root are searched at max_depth.
+        let ply = self.max_depth - depth + 1;
         if let Some(winner) = E::G::get_winner(s) {
-            return winner.evaluate();
+            return winner.evaluate_at_ply(ply);
         }
         if depth == 0 {
             return self.eval.evaluate(s);
         }
         let mut moves = self.move_pool.alloc();
         E::G::generate_moves(s, &mut moves);
-        let mut best = WORST_EVAL;
+        let mut best = WORST_EVAL + ply as Evaluation;
         for m in moves.iter() {
             m.apply(s);
             let value = -self.negamax(s, depth - 1, -beta, -alpha);
@@ -59,7 +87,7 @@ impl<E: Evaluator> Negamax<E> {
             }
         }
         self.move_pool.free(moves);
-        clamp_value(best)
+        best
     }
 }
 
@@ -72,6 +100,7 @@ where
         let mut best = WORST_EVAL;
         let mut moves = self.move_pool.alloc();
         E::G::generate_moves(s, &mut moves);
+        moves.retain(|m| (self.root_filter)(&self.root_moves, m));
         // Randomly permute order that we look at the moves.
         // We'll pick the first best score from this list.
         moves.shuffle(&mut self.rng);
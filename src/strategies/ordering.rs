@@ -0,0 +1,124 @@
+//! Move ordering heuristics that learn from cutoffs elsewhere in the tree.
+//!
+//! Killer moves are moves that caused a beta cutoff at the same ply in a
+//! sibling subtree, and are likely to cause one again. The history heuristic
+//! scores each move index by how often it caused cutoffs, weighted by depth.
+//! The countermove heuristic remembers which move last refuted each of the
+//! opponent's moves.
+
+extern crate parking_lot;
+
+use super::super::interface::*;
+use parking_lot::Mutex;
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// Killer moves are kept for each ply up to the maximum search depth.
+const MAX_PLY: usize = 256;
+// When any history score passes this limit, all scores are halved.
+const HISTORY_LIMIT: u32 = 1 << 24;
+
+// All methods take &self, so the heuristics can be shared between threads.
+// A disabled heuristic has an empty table.
+pub(super) struct MoveOrdering<G: Game> {
+    killers: Vec<Mutex<[Option<G::M>; 2]>>,
+    history: Vec<AtomicU32>,
+    countermoves: Vec<Mutex<Option<G::M>>>,
+}
+
+impl<G: Game> MoveOrdering<G> {
+    pub(super) fn new(killer_moves: bool, history: bool, countermoves: bool) -> Self {
+        let num_indices = G::num_move_indices();
+        Self {
+            killers: if killer_moves {
+                (0..MAX_PLY).map(|_| Mutex::new([None, None])).collect()
+            } else {
+                Vec::new()
+            },
+            history: if history {
+                (0..num_indices).map(|_| AtomicU32::new(0)).collect()
+            } else {
+                Vec::new()
+            },
+            countermoves: if countermoves {
+                (0..num_indices).map(|_| Mutex::new(None)).collect()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+}
+
+impl<G: Game> MoveOrdering<G>
+where
+    G::M: Copy + Eq,
+{
+    fn is_enabled(&self) -> bool {
+        !(self.killers.is_empty() && self.history.is_empty() && self.countermoves.is_empty())
+    }
+
+    // Forget the killer moves, which are specific to the previous root, and
+    // age the history scores.
+    pub(super) fn new_search(&self) {
+        for killers in self.killers.iter() {
+            *killers.lock() = [None; 2];
+        }
+        self.age_history();
+    }
+
+    // Halve every score, without losing bonuses that other threads add in
+    // the meantime.
+    fn age_history(&self) {
+        for score in self.history.iter() {
+            let _ = score.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| Some(s / 2));
+        }
+    }
+
+    fn countermove(&self, prev_move: Option<G::M>) -> Option<G::M> {
+        *self.countermoves.get(G::move_index(&prev_move?))?.lock()
+    }
+
+    // Stably sort the moves, with killer moves first, then the countermove,
+    // then by history score. Other threads may change the scores during the
+    // sort, so each is read once.
+    pub(super) fn order_moves(&self, moves: &mut [G::M], ply: usize, prev_move: Option<G::M>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let killers = self.killers.get(ply).map_or([None; 2], |killers| *killers.lock());
+        let countermove = self.countermove(prev_move);
+        moves.sort_by_cached_key(|&m| {
+            Reverse(if killers[0] == Some(m) {
+                u32::MAX
+            } else if killers[1] == Some(m) {
+                u32::MAX - 1
+            } else if countermove == Some(m) {
+                u32::MAX - 2
+            } else {
+                self.history.get(G::move_index(&m)).map_or(0, |score| score.load(Ordering::Relaxed))
+            })
+        });
+    }
+
+    // Learn from a move that caused a beta cutoff.
+    pub(super) fn record_cutoff(&self, m: G::M, depth: u8, ply: usize, prev_move: Option<G::M>) {
+        if let Some(killers) = self.killers.get(ply) {
+            let mut killers = killers.lock();
+            if killers[0] != Some(m) {
+                killers[1] = killers[0];
+                killers[0] = Some(m);
+            }
+        }
+        if let Some(score) = self.history.get(G::move_index(&m)) {
+            let bonus = depth as u32 * depth as u32;
+            if score.fetch_add(bonus, Ordering::Relaxed) + bonus > HISTORY_LIMIT {
+                self.age_history();
+            }
+        }
+        if let Some(prev_move) = prev_move {
+            if let Some(countermove) = self.countermoves.get(G::move_index(&prev_move)) {
+                *countermove.lock() = Some(m);
+            }
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! A solver using depth-first proof-number search (df-pn).
+//!
+//! Instead of estimating the value of a position with an `Evaluator`, this
+//! tries to prove the exact game-theoretic result. Each node tracks a proof
+//! number and a disproof number: the minimum number of leaves that would
+//! need to be resolved to prove or disprove it. The search always expands
+//! the most proving node, which tends to find forced wins with far fewer
+//! nodes than alpha-beta search. The numbers are stored in a transposition
+//! table keyed by Zobrist hash.
+//!
+//! Proof-number search answers yes-or-no questions, so to distinguish
+//! draws, it first tries to prove a win for the player to move, then a win
+//! for their opponent. If neither can win, the position is a draw.
+
+use super::super::interface::*;
+use super::super::util::*;
+use super::util::*;
+
+use instant::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The game-theoretic value of a position, from the perspective of the
+/// player to move.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// The result of a proof search.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Proof {
+    /// The position was solved.
+    Proven(Outcome),
+    /// The search hit the timeout first.
+    Unknown,
+}
+
+/// Options to use for the proof-number search engine.
+#[derive(Clone, Copy)]
+pub struct PnsOptions {
+    table_byte_size: usize,
+}
+
+impl PnsOptions {
+    pub fn new() -> Self {
+        PnsOptions { table_byte_size: 32_000_000 }
+    }
+}
+
+impl Default for PnsOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PnsOptions {
+    /// Approximately how large the transposition table should be in memory.
+    pub fn with_table_byte_size(mut self, size: usize) -> Self {
+        self.table_byte_size = size;
+        self
+    }
+}
+
+// Proof and disproof numbers saturate at infinity.
+const INFINITY: u32 = u32::MAX;
+
+// Proof numbers are stored in the negamax style, from the perspective of the
+// player to move. phi is the proof number for the player to move reaching
+// their goal, and delta is the disproof number.
+#[derive(Copy, Clone)]
+struct PnsEntry {
+    hash: u64,
+    phi: u32,
+    delta: u32,
+}
+
+impl PnsEntry {
+    fn is_solved(&self) -> bool {
+        self.phi == 0 || self.delta == 0
+    }
+}
+
+// Proof numbers of a node with a known result. The attacker's goal is to win,
+// and the defender's goal is to prevent that.
+fn solved_numbers(attacker_wins: bool, attacker_to_move: bool) -> (u32, u32) {
+    if attacker_wins == attacker_to_move {
+        (0, INFINITY)
+    } else {
+        (INFINITY, 0)
+    }
+}
+
+pub struct ProofNumberSearch<G: Game> {
+    table: Vec<PnsEntry>,
+    mask: usize,
+    max_time: Duration,
+    timeout: Arc<AtomicBool>,
+    move_pool: MovePool<G::M>,
+    // Position hashes from the root to the current node, to avoid cycles.
+    path: Vec<u64>,
+
+    // Runtime stats for the last position solved.
+    nodes_explored: u64,
+    pv: Vec<G::M>,
+}
+
+impl<G: Game> ProofNumberSearch<G>
+where
+    G::S: Clone + Zobrist,
+    G::M: Copy + Eq,
+{
+    pub fn new(opts: PnsOptions) -> Self {
+        let size = (opts.table_byte_size / std::mem::size_of::<PnsEntry>()).next_power_of_two();
+        ProofNumberSearch {
+            table: vec![PnsEntry { hash: 0, phi: 1, delta: 1 }; size],
+            mask: size - 1,
+            max_time: Duration::new(0, 0),
+            timeout: Arc::new(AtomicBool::new(false)),
+            move_pool: MovePool::default(),
+            path: Vec::new(),
+            nodes_explored: 0,
+            pv: Vec::new(),
+        }
+    }
+
+    /// Set the maximum time to spend solving. If the timeout is hit, the
+    /// result is `Proof::Unknown`. By default there is no timeout.
+    pub fn set_timeout(&mut self, max_time: Duration) {
+        self.max_time = max_time;
+    }
+
+    /// Return a human-readable summary of the last position solved.
+    pub fn stats(&self) -> String {
+        format!("Explored {} nodes.", self.nodes_explored)
+    }
+
+    /// Return the line of play that proves the result of the last position
+    /// solved. The moves of the player who can force the result are chosen
+    /// to achieve it, while the other player's replies are arbitrary.
+    pub fn principal_variation(&self) -> &[G::M] {
+        &self.pv[..]
+    }
+
+    /// Try to find the exact result of the position, with perfect play
+    /// from both sides.
+    pub fn solve(&mut self, s: &G::S) -> Proof {
+        self.nodes_explored = 0;
+        self.pv.clear();
+        self.timeout = if self.max_time == Duration::new(0, 0) {
+            Arc::new(AtomicBool::new(false))
+        } else {
+            timeout_signal(self.max_time)
+        };
+
+        let mut s_clone = s.clone();
+        // First, can the player to move force a win?
+        match self.prove(&mut s_clone, true) {
+            Some(true) => return Proof::Proven(Outcome::Win),
+            Some(false) => {}
+            None => return Proof::Unknown,
+        }
+        // Otherwise, can their opponent force a win?
+        match self.prove(&mut s_clone, false) {
+            Some(true) => Proof::Proven(Outcome::Loss),
+            Some(false) => Proof::Proven(Outcome::Draw),
+            None => Proof::Unknown,
+        }
+    }
+
+    // Prove whether the attacker can force a win from this position.
+    // Returns None on timeout. On success, populates the principal variation.
+    fn prove(&mut self, s: &mut G::S, attacker_to_move: bool) -> Option<bool> {
+        // Results for a different attacker are not valid.
+        for entry in self.table.iter_mut() {
+            *entry = PnsEntry { hash: 0, phi: 1, delta: 1 };
+        }
+        let entry = self.solve_node(s, attacker_to_move)?;
+        self.populate_pv(s, attacker_to_move);
+        // Whether the player to move at the root reached their goal.
+        let proven = entry.phi == 0;
+        Some(proven == attacker_to_move)
+    }
+
+    // Search until the node is solved. Returns None on timeout.
+    fn solve_node(&mut self, s: &mut G::S, attacker_to_move: bool) -> Option<PnsEntry> {
+        loop {
+            let entry = self.mid(s, INFINITY - 1, INFINITY - 1, attacker_to_move)?;
+            if entry.is_solved() {
+                return Some(entry);
+            }
+        }
+    }
+
+    fn lookup(&self, hash: u64) -> Option<PnsEntry> {
+        let entry = self.table[(hash as usize) & self.mask];
+        if entry.hash == hash {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    fn store(&mut self, hash: u64, phi: u32, delta: u32) -> PnsEntry {
+        let entry = PnsEntry { hash, phi, delta };
+        self.table[(hash as usize) & self.mask] = entry;
+        entry
+    }
+
+    // Proof numbers of a terminal state, or None if it is not terminal.
+    fn terminal(s: &G::S, attacker_to_move: bool) -> Option<(u32, u32)> {
+        let attacker_wins = match G::get_winner(s)? {
+            Winner::PlayerJustMoved => !attacker_to_move,
+            Winner::PlayerToMove => attacker_to_move,
+            Winner::Draw => false,
+        };
+        Some(solved_numbers(attacker_wins, attacker_to_move))
+    }
+
+    // Proof numbers of a child from the table, or the given estimate if it
+    // is missing.
+    fn child_numbers(
+        &mut self, s: &mut G::S, m: G::M, attacker_to_move: bool, estimate: (u32, u32),
+    ) -> (u32, u32) {
+        m.apply(s);
+        let hash = s.zobrist_hash();
+        let numbers = if let Some(entry) = self.lookup(hash) {
+            (entry.phi, entry.delta)
+        } else if let Some((phi, delta)) = Self::terminal(s, !attacker_to_move) {
+            self.store(hash, phi, delta);
+            (phi, delta)
+        } else if self.path.contains(&hash) {
+            // A cycle is as good as a draw.
+            solved_numbers(false, !attacker_to_move)
+        } else {
+            estimate
+        };
+        m.undo(s);
+        numbers
+    }
+
+    // Multiple iterative deepening: expand the most proving node until the
+    // proof or disproof number of this node reaches its threshold.
+    fn mid(
+        &mut self, s: &mut G::S, phi_threshold: u32, delta_threshold: u32, attacker_to_move: bool,
+    ) -> Option<PnsEntry> {
+        if self.timeout.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.nodes_explored += 1;
+
+        let hash = s.zobrist_hash();
+        if let Some((phi, delta)) = Self::terminal(s, attacker_to_move) {
+            return Some(self.store(hash, phi, delta));
+        }
+        let mut moves = self.move_pool.alloc();
+        G::generate_moves(s, &mut moves);
+        if moves.is_empty() {
+            // No moves is a loss, as in the other strategies.
+            self.move_pool.free(moves);
+            let (phi, delta) = solved_numbers(!attacker_to_move, attacker_to_move);
+            return Some(self.store(hash, phi, delta));
+        }
+
+        self.path.push(hash);
+        // The latest numbers of each child, in case they are evicted from the
+        // table. Otherwise the search could repeat forever.
+        let mut children = vec![(1, 1); moves.len()];
+        let result = loop {
+            // phi is the minimum delta of the children, and delta is the sum
+            // of their phis.
+            let mut phi = INFINITY;
+            let mut delta: u32 = 0;
+            let mut best = 0;
+            let mut best_phi = INFINITY;
+            let mut second_delta = INFINITY;
+            for (i, &m) in moves.iter().enumerate() {
+                children[i] = self.child_numbers(s, m, attacker_to_move, children[i]);
+                let (child_phi, child_delta) = children[i];
+                delta = delta.saturating_add(child_phi);
+                if child_delta < phi {
+                    second_delta = phi;
+                    phi = child_delta;
+                    best = i;
+                    best_phi = child_phi;
+                } else if child_delta < second_delta {
+                    second_delta = child_delta;
+                }
+            }
+            if phi >= phi_threshold || delta >= delta_threshold {
+                break Some(self.store(hash, phi, delta));
+            }
+            // Store the current numbers in case the child search transposes
+            // back into this node.
+            self.store(hash, phi, delta);
+
+            let child_phi_threshold =
+                delta_threshold.saturating_sub(delta - best_phi).min(INFINITY - 1);
+            let child_delta_threshold = phi_threshold.min(second_delta.saturating_add(1));
+            let m = moves[best];
+            m.apply(s);
+            let child = self.mid(s, child_phi_threshold, child_delta_threshold, !attacker_to_move);
+            m.undo(s);
+            match child {
+                Some(child) => children[best] = (child.phi, child.delta),
+                None => break None,
+            }
+        };
+        self.path.pop();
+        self.move_pool.free(moves);
+        result
+    }
+
+    // Follow the proof down the tree from a solved root.
+    fn populate_pv(&mut self, s: &mut G::S, mut attacker_to_move: bool) {
+        self.pv.clear();
+        let mut moves = Vec::new();
+        loop {
+            let hash = s.zobrist_hash();
+            let entry = match self.lookup(hash) {
+                Some(entry) if entry.is_solved() => entry,
+                // Evicted from the table, so search it again.
+                _ => match self.solve_node(s, attacker_to_move) {
+                    Some(entry) => entry,
+                    None => break,
+                },
+            };
+            if G::get_winner(s).is_some() || self.path.contains(&hash) {
+                break;
+            }
+            moves.clear();
+            G::generate_moves(s, &mut moves);
+            let mut next = moves.first().copied();
+            if entry.phi == 0 {
+                // The player to move can reach their goal, find a move that
+                // does it.
+                for &m in moves.iter() {
+                    m.apply(s);
+                    let child = match self.lookup(s.zobrist_hash()) {
+                        Some(child) if child.is_solved() => Some(child),
+                        _ => self.solve_node(s, !attacker_to_move),
+                    };
+                    m.undo(s);
+                    if child.is_some_and(|child| child.delta == 0) {
+                        next = Some(m);
+                        break;
+                    }
+                }
+            }
+            let m = match next {
+                Some(m) => m,
+                None => break,
+            };
+            self.path.push(hash);
+            self.pv.push(m);
+            m.apply(s);
+            attacker_to_move = !attacker_to_move;
+        }
+        self.path.clear();
+        // Restore state.
+        for m in self.pv.iter().rev() {
+            m.undo(s);
+        }
+    }
+}
+
+impl<G: Game> Strategy<G> for ProofNumberSearch<G>
+where
+    G::S: Clone + Zobrist,
+    G::M: Copy + Eq,
+{
+    /// Solve the position, and play the first move of the proof. If the
+    /// position can't be won or drawn, or can't be solved in time, an
+    /// arbitrary move is chosen.
+    fn choose_move(&mut self, s: &G::S) -> Option<G::M> {
+        self.solve(s);
+        self.pv.first().copied().or_else(|| {
+            let mut moves = Vec::new();
+            G::generate_moves(s, &mut moves);
+            moves.first().copied()
+        })
+    }
+}
@@ -0,0 +1,166 @@
+//! Pondering: searching on the opponent's time.
+//!
+//! After choosing a move, the principal variation predicts the opponent's
+//! reply. A `PonderingStrategy` searches the predicted position in a
+//! background thread while the opponent thinks. If the opponent plays the
+//! predicted move (a ponder hit), the background search becomes the real
+//! search, with the usual time limit on top of the time it already had.
+//! Otherwise, the background search is aborted, and the real search starts
+//! with a transposition table warmed up by the ponder search.
+
+use super::super::interface::*;
+use super::util::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{spawn, JoinHandle};
+use instant::Duration;
+
+/// A search that can run until signaled to stop, for pondering.
+pub trait Ponderable<G: Game>: Strategy<G> {
+    /// Choose a move like `choose_move`, but ignore the timeout and search
+    /// until the maximum depth or until `stop` is set.
+    fn choose_move_until(&mut self, state: &G::S, stop: Arc<AtomicBool>) -> Option<G::M>;
+
+    /// The time limit of `choose_move`, or zero if unlimited.
+    fn timeout(&self) -> Duration;
+
+    /// The principal variation found by the last search.
+    fn principal_variation(&self) -> &[G::M];
+}
+
+// A search running in the background on the predicted position.
+struct PonderSearch<G: Game, S> {
+    hash: u64,
+    timeout: Duration,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<(S, Option<G::M>)>,
+}
+
+impl<G: Game, S> PonderSearch<G, S> {
+    fn abort(self) -> S {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.join().unwrap().0
+    }
+}
+
+/// Wraps a search to think on the opponent's time.
+///
+/// Call `start_pondering` with the state after playing each move from
+/// `choose_move`, and the next `choose_move` will pick up where the
+/// background search left off.
+pub struct PonderingStrategy<G: Game, S> {
+    // Only None while pondering, when the background thread owns it.
+    search: Option<S>,
+    pondering: Option<PonderSearch<G, S>>,
+    predicted_reply: Option<G::M>,
+}
+
+impl<G, S> PonderingStrategy<G, S>
+where
+    G: Game,
+    G::S: Clone + Zobrist + Send + 'static,
+    G::M: Copy + Eq + Send + 'static,
+    S: Ponderable<G> + Send + 'static,
+{
+    pub fn new(search: S) -> Self {
+        Self { search: Some(search), pondering: None, predicted_reply: None }
+    }
+
+    /// Start searching in the background on the position after the
+    /// opponent's predicted reply. Returns false if the last search didn't
+    /// predict a legal reply from this state.
+    pub fn start_pondering(&mut self, state: &G::S) -> bool {
+        self.stop_pondering();
+        let reply = match self.predicted_reply.take() {
+            Some(reply) => reply,
+            None => return false,
+        };
+        let mut moves = Vec::new();
+        if G::get_winner(state).is_none() {
+            G::generate_moves(state, &mut moves);
+        }
+        if !moves.contains(&reply) {
+            return false;
+        }
+        let mut predicted = state.clone();
+        reply.apply(&mut predicted);
+        if G::get_winner(&predicted).is_some() {
+            return false;
+        }
+
+        let mut search = self.search.take().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop2 = stop.clone();
+        self.pondering = Some(PonderSearch {
+            hash: predicted.zobrist_hash(),
+            timeout: search.timeout(),
+            stop,
+            handle: spawn(move || {
+                let m = search.choose_move_until(&predicted, stop2);
+                (search, m)
+            }),
+        });
+        true
+    }
+
+    /// Whether a background search is running.
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.is_some()
+    }
+
+    /// Abort the background search. What it stored in the transposition
+    /// table is kept for the next search.
+    pub fn stop_pondering(&mut self) {
+        if let Some(pondering) = self.pondering.take() {
+            self.search = Some(pondering.abort());
+        }
+    }
+
+    /// The wrapped search, e.g. to change its settings. Stops pondering.
+    pub fn inner_mut(&mut self) -> &mut S {
+        self.stop_pondering();
+        self.search.as_mut().unwrap()
+    }
+
+    fn finish_search(&mut self, search: S, m: Option<G::M>) -> Option<G::M> {
+        // The principal variation starts with our move.
+        self.predicted_reply = search.principal_variation().get(1).copied();
+        self.search = Some(search);
+        m
+    }
+}
+
+impl<G, S> Strategy<G> for PonderingStrategy<G, S>
+where
+    G: Game,
+    G::S: Clone + Zobrist + Send + 'static,
+    G::M: Copy + Eq + Send + 'static,
+    S: Ponderable<G> + Send + 'static,
+{
+    fn choose_move(&mut self, state: &G::S) -> Option<G::M> {
+        if let Some(pondering) = self.pondering.take() {
+            if pondering.hash == state.zobrist_hash() {
+                // Ponder hit: give the background search the usual time to
+                // finish.
+                if pondering.timeout != Duration::new(0, 0) {
+                    signal_after(pondering.stop.clone(), pondering.timeout);
+                }
+                let (search, m) = pondering.handle.join().unwrap();
+                return self.finish_search(search, m);
+            }
+            self.search = Some(pondering.abort());
+        }
+        let mut search = self.search.take().unwrap();
+        let m = search.choose_move(state);
+        self.finish_search(search, m)
+    }
+}
+
+impl<G: Game, S> Drop for PonderingStrategy<G, S> {
+    fn drop(&mut self) {
+        if let Some(pondering) = self.pondering.take() {
+            pondering.abort();
+        }
+    }
+}
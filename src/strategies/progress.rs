@@ -0,0 +1,110 @@
+//! Reporting the progress of a search while it runs.
+//!
+//! A callback receives a report after each completed iteration of iterative
+//! deepening, and periodically during long iterations, e.g. to show live
+//! analysis in a UI.
+
+use super::super::interface::*;
+
+use instant::{Duration, Instant};
+
+/// A snapshot of a search in progress.
+pub struct SearchProgress<'a, M> {
+    /// The depth of the last completed iteration.
+    pub depth: u8,
+    /// Whether this report is for the completion of the iteration. Reports
+    /// made during the next iteration repeat its depth, value and principal
+    /// variation with updated node counts.
+    pub complete: bool,
+    /// The value of the root for the player to move.
+    pub value: Evaluation,
+    /// Nodes explored so far in this search. `LazySmp` only counts the
+    /// nodes of its main thread.
+    pub nodes: u64,
+    pub nodes_per_sec: u64,
+    pub elapsed: Duration,
+    /// The principal variation, starting with the best move.
+    pub pv: &'a [M],
+}
+
+type Callback<M> = Box<dyn FnMut(&SearchProgress<M>) + Send>;
+
+pub(super) struct Progress<M> {
+    callback: Option<Callback<M>>,
+    interval: Duration,
+    start: Instant,
+    last_report: Instant,
+    // Results of the last completed iteration.
+    depth: u8,
+    value: Evaluation,
+    pv: Vec<M>,
+}
+
+impl<M> Progress<M> {
+    pub(super) fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            callback: None,
+            interval: Duration::new(0, 0),
+            start: now,
+            last_report: now,
+            depth: 0,
+            value: 0,
+            pv: Vec::new(),
+        }
+    }
+
+    pub(super) fn set_callback(&mut self, interval: Duration, callback: Callback<M>) {
+        self.interval = interval;
+        self.callback = Some(callback);
+    }
+
+    pub(super) fn start(&mut self) {
+        self.start = Instant::now();
+        self.last_report = self.start;
+        self.depth = 0;
+        self.value = 0;
+        self.pv.clear();
+    }
+
+    // Called every so often during the search.
+    pub(super) fn tick(&mut self, nodes: u64) {
+        if self.callback.is_some() && self.last_report.elapsed() >= self.interval {
+            self.report(nodes, false);
+        }
+    }
+
+    fn report(&mut self, nodes: u64, complete: bool) {
+        let now = Instant::now();
+        self.last_report = now;
+        let elapsed = now - self.start;
+        let progress = SearchProgress {
+            depth: self.depth,
+            complete,
+            value: self.value,
+            nodes,
+            nodes_per_sec: (nodes as f64 / elapsed.as_secs_f64()) as u64,
+            elapsed,
+            pv: &self.pv,
+        };
+        if let Some(callback) = self.callback.as_mut() {
+            callback(&progress);
+        }
+    }
+}
+
+impl<M: Copy> Progress<M> {
+    pub(super) fn iteration(&mut self, depth: u8, value: Evaluation, nodes: u64, pv: &[M]) {
+        if self.callback.is_none() {
+            return;
+        }
+        self.depth = depth;
+        self.value = value;
+        self.pv.clear();
+        self.pv.extend_from_slice(pv);
+        self.report(nodes, true);
+    }
+}
+
+// How many nodes to search between checks of whether to report progress.
+pub(super) const TICK_NODES: u64 = 1024;
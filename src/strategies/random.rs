@@ -1,15 +1,22 @@
 //! A strategy that randomly chooses a move, for use in tests.
 
 use super::super::interface::*;
+use super::util::seeded_rng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 
 pub struct Random {
-    rng: rand::rngs::ThreadRng,
+    rng: StdRng,
 }
 
 impl Random {
     pub fn new() -> Random {
-        Random { rng: rand::thread_rng() }
+        Random { rng: seeded_rng(None, 0) }
+    }
+
+    /// Seed the choices, so that the same seed plays the same moves.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = seeded_rng(Some(seed), 0);
     }
 }
 
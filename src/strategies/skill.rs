@@ -0,0 +1,205 @@
+//! A strategy that plays at an adjustable strength, for weaker opponents.
+//!
+//! Each move of the root is scored by an `IterativeSearch`, and a move is
+//! picked at random with a softmax over the scores, so that moves that are
+//! only a little worse are often played, and blunders rarely. Weaker levels
+//! also misjudge positions with some noise added to the evaluation, and
+//! sometimes search less deep.
+
+use super::super::interface::*;
+use super::iterative::{IterativeOptions, IterativeSearch};
+use super::util::seeded_rng;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// The strongest level, which always plays the best move found.
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// Options to use for the skill level strategy.
+#[derive(Clone, Copy)]
+pub struct SkillOptions {
+    level: u8,
+    max_depth: u8,
+    eval_scale: Evaluation,
+    seed: Option<u64>,
+}
+
+impl SkillOptions {
+    pub fn new() -> Self {
+        SkillOptions { level: MAX_SKILL_LEVEL, max_depth: 4, eval_scale: 100, seed: None }
+    }
+}
+
+impl Default for SkillOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SkillOptions {
+    /// How well to play, from 1 to `MAX_SKILL_LEVEL`. Panics outside that
+    /// range.
+    pub fn with_level(mut self, level: u8) -> Self {
+        assert!((1..=MAX_SKILL_LEVEL).contains(&level), "skill level {} out of range", level);
+        self.level = level;
+        self
+    }
+
+    /// The depth to search at full strength.
+    pub fn with_max_depth(mut self, depth: u8) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// How much evaluation a small but clear advantage is worth in this
+    /// game, which scales the evaluation noise and how often worse moves
+    /// are played. Defaults to 100.
+    pub fn with_eval_scale(mut self, scale: Evaluation) -> Self {
+        self.eval_scale = scale;
+        self
+    }
+
+    /// Seed the choices, so that the same seed plays the same moves.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    // How far below full strength, from 0 at the top level to 1 at level 1.
+    fn weakness(&self) -> f64 {
+        (MAX_SKILL_LEVEL - self.level) as f64 / (MAX_SKILL_LEVEL - 1) as f64
+    }
+
+    // The softmax temperature, in evaluation units.
+    fn temperature(&self) -> f64 {
+        3.0 * self.eval_scale as f64 * self.weakness()
+    }
+
+    // The largest noise added to each evaluation.
+    fn noise(&self) -> Evaluation {
+        (2.0 * self.eval_scale as f64 * self.weakness()) as Evaluation
+    }
+
+    // The chance of searching less deep for a move.
+    fn shallow_chance(&self) -> f64 {
+        self.weakness() / 2.0
+    }
+}
+
+// Adds noise to each evaluation. The noise is the same for the same
+// position, so the search stays consistent, but it differs between players.
+struct NoisyEvaluator<E> {
+    eval: E,
+    noise: Evaluation,
+    salt: u64,
+}
+
+impl<E: Evaluator> Evaluator for NoisyEvaluator<E>
+where
+    <E::G as Game>::S: Zobrist,
+{
+    type G = E::G;
+    fn evaluate(&self, s: &<E::G as Game>::S) -> Evaluation {
+        let value = self.eval.evaluate(s);
+        if self.noise == 0 {
+            return value;
+        }
+        // Mix the bits of the hash, from splitmix64.
+        let mut x = s.zobrist_hash() ^ self.salt;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^= x >> 31;
+        let noise = (x % (2 * self.noise as u64 + 1)) as Evaluation - self.noise;
+        value.saturating_add(noise)
+    }
+}
+
+/// Plays at a skill level from 1 to `MAX_SKILL_LEVEL`. The top level plays
+/// like an `IterativeSearch` to the same depth.
+pub struct SkillLevel<E: Evaluator>
+where
+    <E::G as Game>::S: Zobrist,
+{
+    search: IterativeSearch<NoisyEvaluator<E>>,
+    opts: SkillOptions,
+    rng: StdRng,
+}
+
+impl<E: Evaluator> SkillLevel<E>
+where
+    <E::G as Game>::S: Clone + Zobrist,
+    <E::G as Game>::M: Copy + Eq,
+{
+    pub fn new(eval: E, opts: SkillOptions) -> SkillLevel<E> {
+        let mut rng = seeded_rng(opts.seed, 0);
+        let eval = NoisyEvaluator { eval, noise: opts.noise(), salt: rng.gen() };
+        let search = IterativeSearch::new(eval, IterativeOptions::new());
+        SkillLevel { search, opts, rng }
+    }
+
+    // The value of the move, from a search of the position after it to one
+    // ply less than depth.
+    fn score_move(
+        &mut self, s: &mut <E::G as Game>::S, m: <E::G as Game>::M, depth: u8,
+    ) -> Evaluation {
+        m.apply(s);
+        let value = if let Some(winner) = E::G::get_winner(s) {
+            -winner.evaluate_at_ply(1)
+        } else {
+            self.search.set_max_depth(depth.saturating_sub(1) as usize);
+            match self.search.choose_move(s) {
+                // Wins and losses are one ply further from this root.
+                Some(_) => match -self.search.root_value() {
+                    value if value > BEST_EVAL - WIN_DISTANCE => value - 1,
+                    value if value < WORST_EVAL + WIN_DISTANCE => value + 1,
+                    value => value,
+                },
+                // The opponent has no moves, which loses.
+                None => BEST_EVAL - 1,
+            }
+        };
+        m.undo(s);
+        value
+    }
+}
+
+impl<E: Evaluator> Strategy<E::G> for SkillLevel<E>
+where
+    <E::G as Game>::S: Clone + Zobrist,
+    <E::G as Game>::M: Copy + Eq,
+{
+    fn choose_move(&mut self, s: &<E::G as Game>::S) -> Option<<E::G as Game>::M> {
+        let mut moves = Vec::new();
+        E::G::generate_moves(s, &mut moves);
+        if moves.is_empty() {
+            return None;
+        }
+        let mut depth = self.opts.max_depth;
+        if depth > 1 && self.rng.gen_bool(self.opts.shallow_chance()) {
+            depth = self.rng.gen_range(1..depth);
+        }
+
+        let mut s_clone = s.clone();
+        let values: Vec<Evaluation> =
+            moves.iter().map(|&m| self.score_move(&mut s_clone, m, depth)).collect();
+        let best = *values.iter().max().unwrap();
+        let temperature = self.opts.temperature();
+        // Weigh the moves with a softmax over the values. At full strength,
+        // only the best moves have any weight.
+        let choices: Vec<(<E::G as Game>::M, f64)> = moves
+            .iter()
+            .zip(values.iter())
+            .map(|(&m, &value)| {
+                let weight = if temperature == 0.0 {
+                    (value == best) as u8 as f64
+                } else {
+                    ((value as f64 - best as f64) / temperature).exp()
+                };
+                (m, weight)
+            })
+            .collect();
+        choices.choose_weighted(&mut self.rng, |&(_, weight)| weight).ok().map(|&(m, _)| m)
+    }
+}
@@ -0,0 +1,98 @@
+//! Statistics about the last search of an iterative deepening strategy.
+
+use std::fmt;
+use instant::Duration;
+
+/// Counters from the last search, for tuning and debugging. `Display` gives
+/// a human-readable summary.
+#[derive(Clone, Debug, Default)]
+pub struct SearchStats {
+    /// The deepest completed iteration.
+    pub depth: u8,
+    /// Nodes explored in each completed iteration. For `LazySmp`, only the
+    /// nodes of the main thread.
+    pub nodes_per_depth: Vec<u64>,
+    /// Nodes explored in the iteration that was interrupted, which are only
+    /// useful for filling the transposition table for the next search.
+    pub partial_nodes: u64,
+    /// Nodes explored by each thread, starting with the thread that called
+    /// `choose_move`. Empty for single-threaded searches.
+    pub thread_nodes: Vec<u64>,
+    /// Transposition table entries that cut off the search.
+    pub table_hits: u64,
+    pub generate_move_calls: u64,
+    pub generated_moves: u64,
+    pub beta_cutoffs: u64,
+    /// Beta cutoffs on the first move searched.
+    pub first_move_cutoffs: u64,
+    /// Zero-window searches run by `MtdfSearch`.
+    pub zero_window_searches: Option<u32>,
+    pub wall_time: Duration,
+}
+
+impl SearchStats {
+    /// Nodes explored by all threads.
+    pub fn total_nodes(&self) -> u64 {
+        if self.thread_nodes.is_empty() {
+            self.nodes_per_depth.iter().sum::<u64>() + self.partial_nodes
+        } else {
+            self.thread_nodes.iter().sum()
+        }
+    }
+
+    /// The mean number of moves generated at each node, or 0 if none were.
+    pub fn mean_branching_factor(&self) -> f64 {
+        ratio(self.generated_moves as f64, self.generate_move_calls as f64)
+    }
+
+    /// The branching factor of a uniform tree with as many nodes as the last
+    /// completed iteration.
+    pub fn effective_branching_factor(&self) -> f64 {
+        (*self.nodes_per_depth.last().unwrap_or(&0) as f64).powf((self.depth as f64 + 1.0).recip())
+    }
+
+    /// The percentage of beta cutoffs on the first move searched, or 0 if
+    /// there were no cutoffs.
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        100.0 * ratio(self.first_move_cutoffs as f64, self.beta_cutoffs as f64)
+    }
+
+    /// Nodes explored by all threads per second, or 0 if no time was
+    /// measured.
+    pub fn nodes_per_sec(&self) -> f64 {
+        ratio(self.total_nodes() as f64, self.wall_time.as_secs_f64())
+    }
+}
+
+// Avoids NaN and infinity in the summary of an empty search.
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator == 0.0 {
+        0.0
+    } else {
+        numerator / denominator
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Explored {} nodes to depth {}. MBF={:.1} EBF={:.1}",
+            self.nodes_per_depth.iter().sum::<u64>(),
+            self.depth,
+            self.mean_branching_factor(),
+            self.effective_branching_factor()
+        )?;
+        writeln!(f, "Partial exploration of next depth hit {} nodes.", self.partial_nodes)?;
+        if !self.thread_nodes.is_empty() {
+            let nodes: Vec<String> = self.thread_nodes.iter().map(u64::to_string).collect();
+            writeln!(f, "Nodes per thread: {}.", nodes.join(", "))?;
+        }
+        writeln!(f, "{} transposition table hits.", self.table_hits)?;
+        if let Some(searches) = self.zero_window_searches {
+            writeln!(f, "{} zero-window searches.", searches)?;
+        }
+        writeln!(f, "{:.1}% of cutoffs on the first move.", self.first_move_cutoff_rate())?;
+        write!(f, "{} nodes/sec", self.nodes_per_sec() as usize)
+    }
+}
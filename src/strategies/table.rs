@@ -1,9 +1,12 @@
 extern crate parking_lot;
 
+use super::iterative::Replacement;
+use super::util::{read_u16, read_u64};
 use crate::interface::*;
 use parking_lot::Mutex;
 use std::cmp::{max, min};
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 
 // Common transposition table stuff.
@@ -15,7 +18,7 @@ pub(super) enum EntryFlag {
     Lowerbound,
 }
 
-// TODO: Optimize size. Ideally 16 bytes or less.
+// An unpacked table entry.
 #[derive(Copy, Clone)]
 pub(super) struct Entry<M> {
     pub(super) hash: u64,
@@ -26,11 +29,587 @@ pub(super) struct Entry<M> {
     pub(super) best_move: Option<M>,
 }
 
+// The largest magnitude of other evaluations that can be packed exactly.
+const MAX_PACKED_EVAL: Evaluation = i16::MAX as Evaluation - WIN_DISTANCE;
+
+// Pack a value into 16 bits. Wins and losses keep their distance from
+// BEST_EVAL and WORST_EVAL. Other values too large to pack are replaced by
+// the closest bound that still holds.
+fn pack_value(value: Evaluation, flag: EntryFlag) -> (i16, EntryFlag) {
+    let magnitude = value.abs();
+    let sign = value.signum();
+    if magnitude > BEST_EVAL - WIN_DISTANCE {
+        ((sign * (i16::MAX as Evaluation - (BEST_EVAL - magnitude))) as i16, flag)
+    } else if magnitude <= MAX_PACKED_EVAL {
+        (value as i16, flag)
+    } else if flag == EntryFlag::Exact || (flag == EntryFlag::Lowerbound) == (value > 0) {
+        // The largest packable value is a bound towards zero.
+        let flag = if value > 0 { EntryFlag::Lowerbound } else { EntryFlag::Upperbound };
+        ((sign * MAX_PACKED_EVAL) as i16, flag)
+    } else {
+        // The slowest packable win or loss is a bound away from zero.
+        ((sign * (MAX_PACKED_EVAL + 1)) as i16, flag)
+    }
+}
+
+fn unpack_value(value: i16) -> Evaluation {
+    let magnitude = (value as Evaluation).abs();
+    if magnitude > MAX_PACKED_EVAL {
+        (value.signum() as Evaluation) * (BEST_EVAL - (i16::MAX as Evaluation - magnitude))
+    } else {
+        value as Evaluation
+    }
+}
+
+// Wins and losses are counted in plies from the root, but are stored counted
+// from the node itself, so they stay correct when the same position is
+// reached at another ply.
+fn value_to_table(value: Evaluation, ply: usize) -> Evaluation {
+    if value > BEST_EVAL - WIN_DISTANCE {
+        value.saturating_add(ply as Evaluation)
+    } else if value < WORST_EVAL + WIN_DISTANCE {
+        value.saturating_sub(ply as Evaluation).max(WORST_EVAL)
+    } else {
+        value
+    }
+}
+
+fn value_from_table(value: Evaluation, ply: usize) -> Evaluation {
+    if value > BEST_EVAL - WIN_DISTANCE {
+        value - ply as Evaluation
+    } else if value < WORST_EVAL + WIN_DISTANCE {
+        value + ply as Evaluation
+    } else {
+        value
+    }
+}
+
+// Generations are stored modulo 64.
+const GENERATION_MASK: u8 = 0x3f;
+
+// A move as a slot stores it, which also decides how the slot stores its
+// evaluation.
+pub(super) trait SlotMove {
+    type Value: Copy + Default;
+    const EMPTY: Self;
+    fn pack_value(value: Evaluation, flag: EntryFlag) -> (Self::Value, EntryFlag);
+    fn unpack_value(value: Self::Value) -> Evaluation;
+}
+
+// Unpacked moves keep their evaluations whole.
+impl<M> SlotMove for Option<M> {
+    type Value = Evaluation;
+    const EMPTY: Self = None;
+    fn pack_value(value: Evaluation, flag: EntryFlag) -> (Evaluation, EntryFlag) {
+        (value, flag)
+    }
+    fn unpack_value(value: Evaluation) -> Evaluation {
+        value
+    }
+}
+
+// A move packed by PackableMove::pack, next to a packed evaluation.
+#[derive(Copy, Clone)]
+pub(super) struct PackedMove(u16);
+
+impl SlotMove for PackedMove {
+    type Value = i16;
+    const EMPTY: Self = PackedMove(0);
+    fn pack_value(value: Evaluation, flag: EntryFlag) -> (i16, EntryFlag) {
+        pack_value(value, flag)
+    }
+    fn unpack_value(value: i16) -> Evaluation {
+        unpack_value(value)
+    }
+}
+
+// How a table converts moves of type M to and from its slots.
+pub(super) trait MoveCodec<M> {
+    type Slot: SlotMove + Copy;
+    fn store(&self, m: M) -> Self::Slot;
+    fn load(&self, stored: Self::Slot) -> Option<M>;
+}
+
+// Moves stored as they are.
+pub(super) struct Unpacked;
+
+impl<M: Copy> MoveCodec<M> for Unpacked {
+    type Slot = Option<M>;
+    fn store(&self, m: M) -> Option<M> {
+        Some(m)
+    }
+    fn load(&self, stored: Option<M>) -> Option<M> {
+        stored
+    }
+}
+
+// Moves packed into 16 bits. The table keeps the functions of PackableMove,
+// so that its methods don't need the bound.
+pub(super) struct Packer<M> {
+    pack: fn(&M) -> u16,
+    unpack: fn(u16) -> Option<M>,
+}
+
+impl<M: PackableMove> Packer<M> {
+    pub(super) fn new() -> Self {
+        Packer { pack: M::pack, unpack: M::unpack }
+    }
+}
+
+impl<M> MoveCodec<M> for Packer<M> {
+    type Slot = PackedMove;
+    fn store(&self, m: M) -> PackedMove {
+        PackedMove((self.pack)(&m))
+    }
+    fn load(&self, stored: PackedMove) -> Option<M> {
+        (self.unpack)(stored.0)
+    }
+}
+
+// A table entry packed into 12 bytes for packed moves, or 20 bytes for
+// unpacked 32-bit moves. Only the upper half of the hash is kept to verify the
+// position, as the lower half selects its bucket. The flag and generation
+// share a byte.
+#[derive(Copy, Clone)]
+pub(super) struct Slot<S: SlotMove> {
+    key: u32,
+    value: S::Value,
+    depth: u8,
+    // The generation in the upper 6 bits, and the flag plus one in the lower
+    // 2 bits, or zero for an empty slot.
+    info: u8,
+    best_move: S,
+}
+
+impl<S: SlotMove> Slot<S> {
+    pub(super) fn empty() -> Self {
+        Slot { key: 0, value: S::Value::default(), depth: 0, info: 0, best_move: S::EMPTY }
+    }
+
+    pub(super) fn new(
+        hash: u64, value: Evaluation, depth: u8, flag: EntryFlag, generation: u8, best_move: S,
+    ) -> Self {
+        let (value, flag) = S::pack_value(value, flag);
+        Slot {
+            key: (hash >> 32) as u32,
+            value,
+            depth,
+            info: (generation & GENERATION_MASK) << 2 | (flag as u8 + 1),
+            best_move,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.info & 3 == 0
+    }
+
+    fn generation(&self) -> u8 {
+        self.info >> 2
+    }
+
+    fn matches(&self, hash: u64) -> bool {
+        !self.is_empty() && self.key == (hash >> 32) as u32
+    }
+}
+
+impl<S: SlotMove + Copy> Slot<S> {
+    fn entry<M>(&self, hash: u64, codec: &impl MoveCodec<M, Slot = S>) -> Entry<M> {
+        let flag = match self.info & 3 {
+            1 => EntryFlag::Exact,
+            2 => EntryFlag::Upperbound,
+            _ => EntryFlag::Lowerbound,
+        };
+        Entry {
+            hash,
+            value: S::unpack_value(self.value),
+            depth: self.depth,
+            flag,
+            generation: self.generation(),
+            best_move: codec.load(self.best_move),
+        }
+    }
+}
+
+// Reading slots from tables that own them, lock them, or load them
+// atomically.
+pub(super) trait SlotCell<S: SlotMove> {
+    fn get(&self) -> Slot<S>;
+
+    // The slot, if it holds this position.
+    fn get_matching(&self, hash: u64) -> Option<Slot<S>> {
+        Some(self.get()).filter(|slot| slot.matches(hash))
+    }
+}
+
+impl<S: SlotMove + Copy> SlotCell<S> for Slot<S> {
+    fn get(&self) -> Slot<S> {
+        *self
+    }
+}
+
+impl<S: SlotMove + Copy> SlotCell<S> for Mutex<Slot<S>> {
+    fn get(&self) -> Slot<S> {
+        *self.lock()
+    }
+}
+
+// A slot with a packed move that many threads can read and write without
+// locks, as two 64-bit words. The data word holds the packed value, depth,
+// info and move. The key word holds the slot's key in its upper half XORed
+// with the data, so that reading the words of two different writes gives a
+// key that matches neither position, or a nonzero lower half.
+pub(super) struct AtomicSlot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl AtomicSlot {
+    pub(super) fn new() -> Self {
+        AtomicSlot { key: AtomicU64::new(0), data: AtomicU64::new(0) }
+    }
+
+    // Load the key word with the data removed, and the slot.
+    fn load(&self) -> (u64, Slot<PackedMove>) {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key.load(Ordering::Relaxed) ^ data;
+        let slot = Slot {
+            key: (key >> 32) as u32,
+            value: data as u16 as i16,
+            depth: (data >> 16) as u8,
+            info: (data >> 24) as u8,
+            best_move: PackedMove((data >> 32) as u16),
+        };
+        (key, slot)
+    }
+
+    pub(super) fn set(&self, slot: Slot<PackedMove>) {
+        let data = slot.value as u16 as u64
+            | (slot.depth as u64) << 16
+            | (slot.info as u64) << 24
+            | (slot.best_move.0 as u64) << 32;
+        self.data.store(data, Ordering::Relaxed);
+        self.key.store((slot.key as u64) << 32 ^ data, Ordering::Relaxed);
+    }
+}
+
+impl SlotCell<PackedMove> for AtomicSlot {
+    fn get(&self) -> Slot<PackedMove> {
+        self.load().1
+    }
+
+    // Check the whole key word, which doesn't match after a torn read.
+    fn get_matching(&self, hash: u64) -> Option<Slot<PackedMove>> {
+        let (key, slot) = self.load();
+        if key == hash & !0xffff_ffff && !slot.is_empty() {
+            Some(slot)
+        } else {
+            None
+        }
+    }
+}
+
+// The slots of a table, with moves packed if it was created for a
+// PackableMove.
+pub(super) enum Slots<U, P, M> {
+    Unpacked(Vec<U>),
+    Packed(Vec<P>, Packer<M>),
+}
+
+// Identifies the on-disk format of saved tables.
+const MAGIC: &[u8; 4] = b"MMTT";
+const VERSION: u8 = 2;
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+// Write the table's generation and its entries that hold a move.
+//
+// The header is a 4-byte magic number and a version byte, followed by the
+// entry layout (the sizes of the evaluation and encoded move), the move
+// encoding's name, the generation, the number of low bits of the hashes
+// that are known, and the number of entries. Each entry is its hash, value,
+// depth, flag, generation and move. All integers are little-endian.
+pub(super) fn write_entries<M: SerializableMove, W: Write>(
+    w: &mut W, generation: u8, known_bits: u8, entries: &[Entry<M>],
+) -> Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION, std::mem::size_of::<Evaluation>() as u8])?;
+    w.write_all(&(M::ENCODED_SIZE as u16).to_le_bytes())?;
+    w.write_all(&(M::ENCODING.len() as u16).to_le_bytes())?;
+    w.write_all(M::ENCODING.as_bytes())?;
+    w.write_all(&[generation, known_bits])?;
+    w.write_all(&(entries.len() as u64).to_le_bytes())?;
+    let mut buf = vec![0; M::ENCODED_SIZE];
+    for entry in entries.iter() {
+        w.write_all(&entry.hash.to_le_bytes())?;
+        w.write_all(&entry.value.to_le_bytes())?;
+        w.write_all(&[entry.depth, entry.flag as u8, entry.generation])?;
+        entry.best_move.as_ref().expect("only entries with moves are saved").encode(&mut buf);
+        w.write_all(&buf)?;
+    }
+    Ok(())
+}
+
+// Read the generation, known hash bits and entries written by write_entries.
+pub(super) fn read_entries<M: SerializableMove, R: Read>(
+    r: &mut R,
+) -> Result<(u8, u8, Vec<Entry<M>>)> {
+    let mut header = [0; 6];
+    r.read_exact(&mut header)?;
+    if &header[..4] != MAGIC || header[4] != VERSION {
+        return Err(invalid_data("not a transposition table"));
+    }
+    if header[5] as usize != std::mem::size_of::<Evaluation>()
+        || read_u16(r)? as usize != M::ENCODED_SIZE
+    {
+        return Err(invalid_data("different entry layout"));
+    }
+    let mut encoding = vec![0; read_u16(r)? as usize];
+    r.read_exact(&mut encoding)?;
+    if encoding != M::ENCODING.as_bytes() {
+        return Err(invalid_data("different move encoding"));
+    }
+    let mut generation = [0; 2];
+    r.read_exact(&mut generation)?;
+
+    let mut entries = Vec::new();
+    let mut buf = vec![0; M::ENCODED_SIZE];
+    for _ in 0..read_u64(r)? {
+        let hash = read_u64(r)?;
+        let mut value = [0; std::mem::size_of::<Evaluation>()];
+        r.read_exact(&mut value)?;
+        let mut fields = [0; 3];
+        r.read_exact(&mut fields)?;
+        let flag = match fields[1] {
+            0 => EntryFlag::Exact,
+            1 => EntryFlag::Upperbound,
+            2 => EntryFlag::Lowerbound,
+            _ => return Err(invalid_data("invalid entry flag")),
+        };
+        r.read_exact(&mut buf)?;
+        let best_move = M::decode(&buf).ok_or_else(|| invalid_data("invalid move"))?;
+        entries.push(Entry {
+            hash,
+            value: Evaluation::from_le_bytes(value),
+            depth: fields[0],
+            flag,
+            generation: fields[2],
+            best_move: Some(best_move),
+        });
+    }
+    Ok((generation[0], generation[1], entries))
+}
+
+#[test]
+fn test_table_value() {
+    // A win in 5 plies from the root, found 2 plies from the root, is a win
+    // in 3 plies from that node, and a win in 6 plies when it is reached
+    // again 3 plies from the root.
+    assert_eq!(BEST_EVAL - 3, value_to_table(BEST_EVAL - 5, 2));
+    assert_eq!(BEST_EVAL - 6, value_from_table(BEST_EVAL - 3, 3));
+    assert_eq!(WORST_EVAL + 3, value_to_table(WORST_EVAL + 5, 2));
+    assert_eq!(WORST_EVAL + 6, value_from_table(WORST_EVAL + 3, 3));
+    assert_eq!(17, value_from_table(value_to_table(17, 4), 4));
+}
+
 #[test]
 fn test_entry_size() {
-    // TODO: ratchet down
-    assert!(std::mem::size_of::<Entry<u32>>() <= 24);
-    assert!(std::mem::size_of::<Mutex<Entry<u32>>>() <= 32);
+    assert!(std::mem::size_of::<Slot<PackedMove>>() <= 12);
+    assert!(std::mem::size_of::<Slot<Option<u32>>>() <= 20);
+    assert!(std::mem::size_of::<AtomicSlot>() <= 16);
+}
+
+#[test]
+fn test_pack_value() {
+    for value in
+        [0, 1, -1, 1000, MAX_PACKED_EVAL, BEST_EVAL, BEST_EVAL - WIN_DISTANCE + 1, WORST_EVAL + 5]
+    {
+        for flag in [EntryFlag::Exact, EntryFlag::Lowerbound, EntryFlag::Upperbound] {
+            let (packed, packed_flag) = pack_value(value, flag);
+            assert_eq!((value, flag), (unpack_value(packed), packed_flag));
+        }
+    }
+    // Bounds that can't be packed exactly are loosened.
+    for value in [MAX_PACKED_EVAL + 1, BEST_EVAL - WIN_DISTANCE, -MAX_PACKED_EVAL - 1] {
+        for flag in [EntryFlag::Exact, EntryFlag::Lowerbound, EntryFlag::Upperbound] {
+            let (packed, packed_flag) = pack_value(value, flag);
+            let unpacked = unpack_value(packed);
+            match packed_flag {
+                EntryFlag::Lowerbound => assert!(unpacked <= value),
+                EntryFlag::Upperbound => assert!(unpacked >= value),
+                EntryFlag::Exact => panic!("{} packed exactly", value),
+            }
+            // Unpacked moves keep them exactly.
+            let slot = Slot::new(0, value, 0, flag, 0, Some(0u32));
+            let entry = slot.entry(0, &Unpacked);
+            assert_eq!((value, flag), (entry.value, entry.flag));
+        }
+    }
+}
+
+const CACHE_LINE: usize = 64;
+
+// How a table with a replacement strategy is divided into buckets of
+// adjacent entries that a hash can be stored in.
+#[derive(Clone, Copy)]
+pub(super) struct Buckets {
+    strategy: Replacement,
+    // Entries per bucket.
+    ways: usize,
+    // Mask over the bucket number.
+    mask: usize,
+    // Index of the first bucket, which starts on a cache line.
+    offset: usize,
+}
+
+impl Buckets {
+    // Allocate the entries of a table of approximately table_byte_size.
+    pub(super) fn new_table<T>(
+        table_byte_size: usize, strategy: Replacement, empty: impl Fn() -> T,
+    ) -> (Self, Vec<T>) {
+        let ways = match strategy {
+            Replacement::Always | Replacement::DepthPreferred => 1,
+            Replacement::TwoTier => 2,
+            Replacement::Bucket(size) => {
+                assert!(size > 0, "buckets must hold at least one entry");
+                size
+            }
+        };
+        let entry_size = std::mem::size_of::<T>().max(1);
+        let num_buckets = (table_byte_size / (entry_size * ways)).max(1).next_power_of_two();
+        // Pad the allocation so that the first bucket can start on a cache
+        // line. Later buckets aren't padded, which would waste whole entries.
+        let padding = CACHE_LINE / std::mem::align_of::<T>();
+        let mut table = Vec::with_capacity(num_buckets * ways + padding);
+        for _ in 0..num_buckets * ways + padding {
+            table.push(empty());
+        }
+        let start = table.as_ptr() as usize;
+        let offset =
+            (0..padding).find(|i| (start + i * entry_size).is_multiple_of(CACHE_LINE)).unwrap_or(0);
+        (Self { strategy, ways, mask: num_buckets - 1, offset }, table)
+    }
+
+    // The indices of the entries that can hold this hash.
+    pub(super) fn bucket(&self, hash: u64) -> std::ops::Range<usize> {
+        let start = self.offset + ((hash as usize) & self.mask) * self.ways;
+        start..start + self.ways
+    }
+
+    pub(super) fn lookup<M, C: MoveCodec<M>>(
+        &self, table: &[impl SlotCell<C::Slot>], codec: &C, hash: u64,
+    ) -> Option<Entry<M>> {
+        table[self.bucket(hash)]
+            .iter()
+            .find_map(|slot| slot.get_matching(hash))
+            .map(|slot| slot.entry(hash, codec))
+    }
+
+    // Which entry to overwrite when storing an entry of depth in generation,
+    // if any.
+    pub(super) fn replace<S: SlotMove + Copy>(
+        &self, table: &[impl SlotCell<S>], hash: u64, depth: u8, generation: u8,
+    ) -> Option<usize> {
+        let bucket = self.bucket(hash);
+        let start = bucket.start;
+        let slots = &table[bucket];
+        let generation = generation & GENERATION_MASK;
+        let dest = match self.strategy {
+            Replacement::Always => Some(0),
+            Replacement::DepthPreferred => {
+                let slot = slots[0].get();
+                if slot.generation() != generation || slot.depth <= depth {
+                    Some(0)
+                } else {
+                    None
+                }
+            }
+            // The first of a pair of entries is the depth-preferred entry and
+            // the second is the always-replace entry.
+            Replacement::TwoTier => {
+                let slot = slots[0].get();
+                if slot.generation() != generation || slot.depth <= depth {
+                    Some(0)
+                } else {
+                    Some(1)
+                }
+            }
+            // Overwrite the same position, or else the entry that is least
+            // useful: empty, old, or shallow.
+            Replacement::Bucket(_) => {
+                let mut victim = (0, i32::MAX);
+                for (i, slot) in slots.iter().map(SlotCell::get).enumerate() {
+                    if slot.matches(hash) {
+                        victim.0 = i;
+                        break;
+                    }
+                    let score = if slot.is_empty() {
+                        i32::MIN
+                    } else {
+                        let age = generation.wrapping_sub(slot.generation()) & GENERATION_MASK;
+                        slot.depth as i32 - 8 * age as i32
+                    };
+                    if score < victim.1 {
+                        victim = (i, score);
+                    }
+                }
+                Some(victim.0)
+            }
+        };
+        dest.map(|i| start + i)
+    }
+
+    // The number of low bits of the hash that select a bucket.
+    pub(super) fn index_bits(&self) -> u8 {
+        self.mask.count_ones() as u8
+    }
+
+    // The entries in the table that hold a move, with the hash bits that
+    // are known from their position in the table.
+    pub(super) fn entries<M, C: MoveCodec<M>>(
+        &self, table: &[impl SlotCell<C::Slot>], codec: &C,
+    ) -> Vec<Entry<M>> {
+        let mut entries = Vec::new();
+        for (i, slot) in table.iter().map(SlotCell::get).enumerate() {
+            if !slot.is_empty() {
+                let index = ((i - self.offset) / self.ways) as u64;
+                let entry = slot.entry((slot.key as u64) << 32 | index, codec);
+                if entry.best_move.is_some() {
+                    entries.push(entry);
+                }
+            }
+        }
+        entries
+    }
+
+    // Where to load a saved entry into empty slots, or over shallower
+    // entries when the table is full. The entry is copied
+    // into each bucket it could belong to, when only known_bits of its hash
+    // are known.
+    pub(super) fn load<M: Copy, C: MoveCodec<M>>(
+        &self, table: &[impl SlotCell<C::Slot>], codec: &C, entry: &Entry<M>, known_bits: u8,
+    ) -> Vec<(usize, Slot<C::Slot>)> {
+        let mut dests = Vec::new();
+        let best_move = entry.best_move.expect("only entries with moves are saved");
+        let copies = 1u64 << self.index_bits().saturating_sub(known_bits);
+        for high_bits in 0..copies {
+            let hash = entry.hash | high_bits << known_bits;
+            let bucket = self.bucket(hash);
+            let start = bucket.start;
+            let slots = &table[bucket];
+            let dest = match slots.iter().position(|slot| slot.get().is_empty()) {
+                Some(i) => start + i,
+                None if slots[0].get().depth <= entry.depth => start,
+                None => continue,
+            };
+            let best_move = codec.store(best_move);
+            let slot =
+                Slot::new(hash, entry.value, entry.depth, entry.flag, entry.generation, best_move);
+            dests.push((dest, slot));
+        }
+        dests
+    }
 }
 
 // A trait for a transposition table. The methods are mutual exclusion, but
@@ -40,29 +619,31 @@ pub(super) trait Table<M: Copy> {
     fn store(&mut self, hash: u64, value: Evaluation, depth: u8, flag: EntryFlag, best_move: M);
     fn advance_generation(&mut self);
 
-    // Check and update negamax state based on any transposition table hit.
+    // Check and update negamax state based on any transposition table hit
+    // for a node ply moves from the root.
     // Returns Some(value) on an exact match.
     // Returns None, updating mutable arguments, if Negamax should continue to explore this node.
     fn check(
-        &self, hash: u64, depth: u8, good_move: &mut Option<M>, alpha: &mut Evaluation,
+        &self, hash: u64, depth: u8, ply: usize, good_move: &mut Option<M>, alpha: &mut Evaluation,
         beta: &mut Evaluation,
     ) -> Option<Evaluation> {
         if let Some(entry) = self.lookup(hash) {
             *good_move = entry.best_move;
             if entry.depth >= depth {
+                let value = value_from_table(entry.value, ply);
                 match entry.flag {
                     EntryFlag::Exact => {
-                        return Some(entry.value);
+                        return Some(value);
                     }
                     EntryFlag::Lowerbound => {
-                        *alpha = max(*alpha, entry.value);
+                        *alpha = max(*alpha, value);
                     }
                     EntryFlag::Upperbound => {
-                        *beta = min(*beta, entry.value);
+                        *beta = min(*beta, value);
                     }
                 }
                 if *alpha >= *beta {
-                    return Some(entry.value);
+                    return Some(value);
                 }
             }
         }
@@ -70,8 +651,9 @@ pub(super) trait Table<M: Copy> {
     }
 
     // Update table based on negamax results.
+    #[allow(clippy::too_many_arguments)]
     fn update(
-        &mut self, hash: u64, alpha_orig: Evaluation, beta: Evaluation, depth: u8,
+        &mut self, hash: u64, alpha_orig: Evaluation, beta: Evaluation, depth: u8, ply: usize,
         best: Evaluation, best_move: M,
     ) {
         let flag = if best <= alpha_orig {
@@ -81,7 +663,7 @@ pub(super) trait Table<M: Copy> {
         } else {
             EntryFlag::Exact
         };
-        self.store(hash, best, depth, flag, best_move);
+        self.store(hash, value_to_table(best, ply), depth, flag, best_move);
     }
 
     // After finishing a search, populate the principal variation as deep as
@@ -93,6 +675,7 @@ pub(super) trait Table<M: Copy> {
     {
         pv.clear();
         let mut hash = s.zobrist_hash();
+        let mut path = Vec::new();
         while let Some(entry) = self.lookup(hash) {
             // The principal variation should only have exact nodes, as other
             // node types are from cutoffs where the node is proven to be
@@ -102,12 +685,17 @@ pub(super) trait Table<M: Copy> {
             // value to be exact, and we can't guarantee that the table entry
             // will remain in the table between the searches that find
             // equivalent upper and lower bounds.
-            let m = entry.best_move.unwrap();
+            let m = match entry.best_move {
+                Some(m) => m,
+                None => break,
+            };
             pv.push(m);
             m.apply(s);
+            path.push(hash);
             hash = s.zobrist_hash();
-            // Prevent cyclical PVs from being infinitely long.
-            if depth == 0 {
+            // Stop at a repeated position, or the PV would be infinitely
+            // long. Stale entries could also extend it past the search depth.
+            if depth == 0 || path.contains(&hash) {
                 break;
             }
             depth -= 1;
@@ -121,147 +709,71 @@ pub(super) trait Table<M: Copy> {
 
 // It would be nice to unify most of the implementation of the single-threaded
 // and concurrent tables, but the methods need different signatures.
+//
+// Only packed moves fit in the two words of an AtomicSlot, so tables of
+// other moves lock each slot with a mutex.
 pub(super) struct ConcurrentTable<M> {
-    table: Vec<Mutex<Entry<M>>>,
-    mask: usize,
+    slots: Slots<Mutex<Slot<Option<M>>>, AtomicSlot, M>,
+    buckets: Buckets,
     // Incremented for each iterative deepening run.
     // Values from old generations are always overwritten.
     generation: AtomicU8,
 }
 
 impl<M> ConcurrentTable<M> {
-    pub(super) fn new(table_byte_size: usize) -> Self {
-        let size = (table_byte_size / std::mem::size_of::<Mutex<Entry<M>>>()).next_power_of_two();
-        let mask = (size - 1) & !1;
-        let mut table = Vec::with_capacity(size);
-        for _ in 0..size {
-            table.push(Mutex::new(Entry::<M> {
-                hash: 0,
-                value: 0,
-                depth: 0,
-                flag: EntryFlag::Exact,
-                generation: 0,
-                best_move: None,
-            }));
-        }
-        Self { table, mask, generation: AtomicU8::new(0) }
+    pub(super) fn new(table_byte_size: usize, strategy: Replacement) -> Self {
+        let (buckets, table) =
+            Buckets::new_table(table_byte_size, strategy, || Mutex::new(Slot::empty()));
+        Self { slots: Slots::Unpacked(table), buckets, generation: AtomicU8::new(0) }
     }
 
-    pub(super) fn concurrent_advance_generation(&self) {
-        self.generation.fetch_add(1, Ordering::SeqCst);
-    }
-}
-
-impl<M: Copy> Table<M> for ConcurrentTable<M> {
-    fn lookup(&self, hash: u64) -> Option<Entry<M>> {
-        self.concurrent_lookup(hash)
-    }
-    fn store(&mut self, hash: u64, value: Evaluation, depth: u8, flag: EntryFlag, best_move: M) {
-        self.concurrent_store(hash, value, depth, flag, best_move)
-    }
-    fn advance_generation(&mut self) {
-        self.concurrent_advance_generation()
+    pub(super) fn new_packed(table_byte_size: usize, strategy: Replacement) -> Self
+    where
+        M: PackableMove,
+    {
+        let (buckets, table) = Buckets::new_table(table_byte_size, strategy, AtomicSlot::new);
+        Self { slots: Slots::Packed(table, Packer::new()), buckets, generation: AtomicU8::new(0) }
     }
-}
 
-impl<M: Copy> Table<M> for Arc<ConcurrentTable<M>> {
-    fn lookup(&self, hash: u64) -> Option<Entry<M>> {
-        self.concurrent_lookup(hash)
-    }
-    fn store(&mut self, hash: u64, value: Evaluation, depth: u8, flag: EntryFlag, best_move: M) {
-        self.concurrent_store(hash, value, depth, flag, best_move)
-    }
-    fn advance_generation(&mut self) {
-        self.concurrent_advance_generation()
+    pub(super) fn concurrent_advance_generation(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 }
 
-impl<M> ConcurrentTable<M>
-where
-    M: Copy,
-{
-    // Using two-tier table, look in the two adjacent slots
-    pub(super) fn concurrent_lookup(&self, hash: u64) -> Option<Entry<M>> {
-        let index = (hash as usize) & self.mask;
-        for i in index..index + 2 {
-            let entry = self.table[i].lock();
-            if hash == entry.hash {
-                return Some(*entry);
-            }
-        }
-        None
-    }
-
-    fn concurrent_store(
-        &self, hash: u64, value: Evaluation, depth: u8, flag: EntryFlag, best_move: M,
-    ) {
-        let table_gen = self.generation.load(Ordering::Relaxed);
-        // index points to the first of a pair of entries, the depth-preferred entry and the always-replace entry.
-        let index = (hash as usize) & self.mask;
-        let new_entry =
-            Entry { hash, value, depth, flag, generation: table_gen, best_move: Some(best_move) };
-        {
-            let mut entry = self.table[index].lock();
-            if entry.generation != table_gen || entry.depth <= depth {
-                *entry = new_entry;
-                return;
-            }
-        }
-        // Otherwise, always overwrite second entry.
-        *self.table[index + 1].lock() = new_entry;
-    }
-
-    // Update table based on negamax results.
-    pub(super) fn concurrent_update(
-        &self, hash: u64, alpha_orig: Evaluation, beta: Evaluation, depth: u8, best: Evaluation,
-        best_move: M,
-    ) {
-        let flag = if best <= alpha_orig {
-            EntryFlag::Upperbound
-        } else if best >= beta {
-            EntryFlag::Lowerbound
-        } else {
-            EntryFlag::Exact
+impl<M: Move + Copy + SerializableMove> ConcurrentTable<M> {
+    pub(super) fn write_to<W: Write>(&self, w: &mut W) -> Result<()> {
+        let entries = match &self.slots {
+            Slots::Unpacked(table) => self.buckets.entries(table, &Unpacked),
+            Slots::Packed(table, packer) => self.buckets.entries(table, packer),
         };
-        self.concurrent_store(hash, best, depth, flag, best_move);
+        let generation = self.generation.load(Ordering::Relaxed);
+        write_entries(w, generation, self.buckets.index_bits(), &entries)
     }
-}
 
-// A concurrent table that doesn't bother to use atomic operations to access its entries.
-// It's crazily unsafe, but somehow StockFish gets away with this?
-pub(super) struct RacyTable<M> {
-    table: Vec<Entry<M>>,
-    mask: usize,
-    // Incremented for each iterative deepening run.
-    // Values from old generations are always overwritten.
-    generation: AtomicU8,
-}
-
-#[allow(dead_code)]
-impl<M> RacyTable<M> {
-    pub(super) fn new(table_byte_size: usize) -> Self {
-        let size = (table_byte_size / std::mem::size_of::<Entry<M>>()).next_power_of_two();
-        let mask = size - 1;
-        let mut table = Vec::with_capacity(size);
-        for _ in 0..size {
-            table.push(Entry::<M> {
-                hash: 0,
-                value: 0,
-                depth: 0,
-                flag: EntryFlag::Exact,
-                generation: 0,
-                best_move: None,
-            });
+    // Read entries written by write_to into empty slots, or over shallower
+    // entries when the table is full.
+    pub(super) fn read_from<R: Read>(&self, r: &mut R) -> Result<()> {
+        let (generation, known_bits, entries) = read_entries(r)?;
+        self.generation.store(generation, Ordering::SeqCst);
+        for entry in entries.iter() {
+            match &self.slots {
+                Slots::Unpacked(table) => {
+                    for (i, slot) in self.buckets.load(table, &Unpacked, entry, known_bits) {
+                        *table[i].lock() = slot;
+                    }
+                }
+                Slots::Packed(table, packer) => {
+                    for (i, slot) in self.buckets.load(table, packer, entry, known_bits) {
+                        table[i].set(slot);
+                    }
+                }
+            }
         }
-        Self { table, mask, generation: AtomicU8::new(0) }
-    }
-
-    pub(super) fn concurrent_advance_generation(&self) {
-        self.generation.fetch_add(1, Ordering::SeqCst);
+        Ok(())
     }
 }
 
-impl<M: Copy> Table<M> for RacyTable<M> {
+impl<M: Move + Copy> Table<M> for ConcurrentTable<M> {
     fn lookup(&self, hash: u64) -> Option<Entry<M>> {
         self.concurrent_lookup(hash)
     }
@@ -273,7 +785,7 @@ impl<M: Copy> Table<M> for RacyTable<M> {
     }
 }
 
-impl<M: Copy> Table<M> for Arc<RacyTable<M>> {
+impl<M: Move + Copy> Table<M> for Arc<ConcurrentTable<M>> {
     fn lookup(&self, hash: u64) -> Option<Entry<M>> {
         self.concurrent_lookup(hash)
     }
@@ -285,44 +797,45 @@ impl<M: Copy> Table<M> for Arc<RacyTable<M>> {
     }
 }
 
-#[allow(dead_code)]
-impl<M> RacyTable<M>
+impl<M> ConcurrentTable<M>
 where
-    M: Copy,
+    M: Move + Copy,
 {
+    // Look in each slot of the bucket.
     pub(super) fn concurrent_lookup(&self, hash: u64) -> Option<Entry<M>> {
-        let index = (hash as usize) & self.mask;
-        let entry = self.table[index];
-        if hash == entry.hash {
-            return Some(entry);
+        match &self.slots {
+            Slots::Unpacked(table) => self.buckets.lookup(table, &Unpacked, hash),
+            Slots::Packed(table, packer) => self.buckets.lookup(table, packer, hash),
         }
-        None
     }
 
+    // Entries are read one at a time, so another thread may overwrite the
+    // chosen slot first. Either entry is as good to keep.
     fn concurrent_store(
         &self, hash: u64, value: Evaluation, depth: u8, flag: EntryFlag, best_move: M,
     ) {
         let table_gen = self.generation.load(Ordering::Relaxed);
-        let index = (hash as usize) & self.mask;
-        let entry = &self.table[index];
-        if entry.generation != table_gen || entry.depth <= depth {
-            #[allow(mutable_transmutes)]
-            let ptr = unsafe { std::mem::transmute::<&Entry<M>, &mut Entry<M>>(entry) };
-            *ptr = Entry {
-                hash,
-                value,
-                depth,
-                flag,
-                generation: table_gen,
-                best_move: Some(best_move),
-            };
+        match &self.slots {
+            Slots::Unpacked(table) => {
+                if let Some(i) = self.buckets.replace(table, hash, depth, table_gen) {
+                    let best_move = Some(best_move);
+                    *table[i].lock() = Slot::new(hash, value, depth, flag, table_gen, best_move);
+                }
+            }
+            Slots::Packed(table, packer) => {
+                if let Some(i) = self.buckets.replace(table, hash, depth, table_gen) {
+                    let best_move = packer.store(best_move);
+                    table[i].set(Slot::new(hash, value, depth, flag, table_gen, best_move));
+                }
+            }
         }
     }
 
     // Update table based on negamax results.
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn concurrent_update(
-        &self, hash: u64, alpha_orig: Evaluation, beta: Evaluation, depth: u8, best: Evaluation,
-        best_move: M,
+        &self, hash: u64, alpha_orig: Evaluation, beta: Evaluation, depth: u8, ply: usize,
+        best: Evaluation, best_move: M,
     ) {
         let flag = if best <= alpha_orig {
             EntryFlag::Upperbound
@@ -331,6 +844,6 @@ where
         } else {
             EntryFlag::Exact
         };
-        self.concurrent_store(hash, best, depth, flag, best_move);
+        self.concurrent_store(hash, value_to_table(best, ply), depth, flag, best_move);
     }
 }
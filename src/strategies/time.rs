@@ -0,0 +1,205 @@
+//! Limits on iterative deepening searches, and time management under a game
+//! clock.
+//!
+//! Each move gets a soft limit, after which no new iteration is started, and
+//! a hard limit, after which the search is aborted. Between iterations, the
+//! search also stops if the next iteration is predicted to overrun the hard
+//! limit, based on how much the previous iterations grew. The soft limit is
+//! extended when the best move changes, as the search is still unsure.
+
+use super::util::timeout_signal;
+
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use instant::{Duration, Instant};
+
+/// The state of the clock for the player to move.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeControl {
+    remaining: Duration,
+    increment: Duration,
+    moves_to_go: Option<u32>,
+}
+
+impl TimeControl {
+    /// The time left on the clock.
+    pub fn new(remaining: Duration) -> Self {
+        TimeControl { remaining, increment: Duration::new(0, 0), moves_to_go: None }
+    }
+
+    /// Time added to the clock after each move.
+    pub fn with_increment(mut self, increment: Duration) -> Self {
+        self.increment = increment;
+        self
+    }
+
+    /// The number of moves until the next time control, when more time is
+    /// added. Otherwise, the remaining time must last the rest of the game.
+    pub fn with_moves_to_go(mut self, moves: u32) -> Self {
+        self.moves_to_go = Some(moves);
+        self
+    }
+}
+
+/// Limits on how long to search for a move. The search stops at whichever
+/// limit it hits first, and returns the best move from the last completed
+/// iteration.
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    depth: Option<usize>,
+    time: Option<Duration>,
+    clock: Option<TimeControl>,
+    nodes: Option<u64>,
+    stop: Option<Arc<AtomicBool>>,
+}
+
+impl SearchLimits {
+    /// No limits: search until stopped or the game is solved.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum depth to search.
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// The maximum time to search.
+    pub fn with_time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Budget the time for the move from the state of the game clock.
+    pub fn with_time_control(mut self, clock: TimeControl) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// The maximum number of nodes to search, not counting quiescence
+    /// search. `LazySmp` only counts the nodes of its main thread.
+    pub fn with_nodes(mut self, nodes: u64) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    /// Stop the search when the token is set, e.g. from a UI thread. The
+    /// token is never reset by the search.
+    pub fn with_stop_token(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    pub(super) fn max_depth(&self) -> usize {
+        self.depth.unwrap_or(100)
+    }
+
+    pub(super) fn node_limit(&self) -> u64 {
+        self.nodes.unwrap_or(u64::MAX)
+    }
+
+    pub(super) fn stop_token(&self) -> Arc<AtomicBool> {
+        self.stop.clone().unwrap_or_else(|| Arc::new(AtomicBool::new(false)))
+    }
+
+    // Start the clock for a search. Returns the timeout signal, and the time
+    // manager if there is a time control.
+    pub(super) fn start_timer(&self) -> (Arc<AtomicBool>, Option<TimeManager>) {
+        let manager = self.clock.map(TimeManager::new);
+        let hard_limit = match (manager.as_ref(), self.time) {
+            (Some(manager), Some(time)) => Some(manager.hard_limit().min(time)),
+            (Some(manager), None) => Some(manager.hard_limit()),
+            (None, time) => time,
+        };
+        let timeout = match hard_limit {
+            Some(time) => timeout_signal(time),
+            None => Arc::new(AtomicBool::new(false)),
+        };
+        (timeout, manager)
+    }
+
+    // The time a search is expected to take, or zero if unlimited.
+    pub(super) fn expected_time(&self) -> Duration {
+        match (self.clock, self.time) {
+            (Some(clock), Some(time)) => TimeManager::new(clock).soft_limit().min(time),
+            (Some(clock), None) => TimeManager::new(clock).soft_limit(),
+            (None, time) => time.unwrap_or_default(),
+        }
+    }
+}
+
+// How many more moves to budget for in sudden death.
+const EXPECTED_MOVES: u32 = 30;
+// How many times the soft limit a move may use if it needs more time.
+const HARD_LIMIT_RATIO: u32 = 4;
+// Never plan to use more than this fraction of the clock on one move.
+const MAX_CLOCK_FRACTION: f64 = 0.8;
+
+pub(super) struct TimeManager {
+    start: Instant,
+    soft_limit: Duration,
+    hard_limit: Duration,
+    last_iteration_start: Instant,
+    // How long each completed iteration took.
+    iteration_times: Vec<Duration>,
+}
+
+impl TimeManager {
+    pub(super) fn new(clock: TimeControl) -> Self {
+        let moves = clock.moves_to_go.unwrap_or(EXPECTED_MOVES).max(1);
+        let max_time = clock.remaining.mul_f64(MAX_CLOCK_FRACTION);
+        let hard_limit = ((clock.remaining + clock.increment * (moves - 1)) / moves
+            * HARD_LIMIT_RATIO)
+            .min(max_time);
+        let soft_limit = (clock.remaining / moves + clock.increment).min(hard_limit);
+        let now = Instant::now();
+        TimeManager {
+            start: now,
+            soft_limit,
+            hard_limit,
+            last_iteration_start: now,
+            iteration_times: Vec::new(),
+        }
+    }
+
+    pub(super) fn soft_limit(&self) -> Duration {
+        self.soft_limit
+    }
+
+    // The search should be aborted after this long.
+    pub(super) fn hard_limit(&self) -> Duration {
+        self.hard_limit
+    }
+
+    // Called after each completed iteration, with the nodes explored at each
+    // depth so far. Returns whether to start the next iteration.
+    pub(super) fn next_iteration(
+        &mut self, nodes_explored: &[u64], best_move_changed: bool,
+    ) -> bool {
+        let now = Instant::now();
+        let last_time = now - self.last_iteration_start;
+        self.last_iteration_start = now;
+        self.iteration_times.push(last_time);
+        if best_move_changed && self.iteration_times.len() > 1 {
+            self.soft_limit = self.soft_limit.mul_f64(1.5).min(self.hard_limit);
+        }
+
+        let elapsed = now - self.start;
+        if elapsed >= self.soft_limit {
+            return false;
+        }
+        // Predict the next iteration from the growth of the last one, in
+        // nodes if they're counted, and otherwise in time.
+        let growth = match *nodes_explored {
+            [.., prev, last] if prev > 0 && last > 0 => last as f64 / prev as f64,
+            _ => match self.iteration_times[..] {
+                [.., prev, last] if prev > Duration::new(0, 0) => {
+                    last.as_secs_f64() / prev.as_secs_f64()
+                }
+                _ => return true,
+            },
+        };
+        elapsed + last_time.mul_f64(growth.max(1.0)) < self.hard_limit
+    }
+}
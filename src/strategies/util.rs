@@ -1,56 +1,47 @@
 use super::super::interface::*;
 
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::io::{Read, Result};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::{sleep, spawn};
+#[cfg(feature = "wasm-bindgen")]
 use gloo_timers::callback::Timeout;
 use instant::Duration;
 
-// For values near winning and losing values, push them slightly closer to zero.
-// A win in 3 moves (BEST-3) will be chosen over a win in 5 moves (BEST-5).
-// A loss in 5 moves (WORST+5) will be chosen over a loss in 3 moves (WORST+3).
-pub(super) fn clamp_value(value: Evaluation) -> Evaluation {
-    if value > BEST_EVAL - 100 {
-        value - 1
-    } else if value < WORST_EVAL + 100 {
-        value + 1
-    } else {
-        value
+// A random number generator for the index-th user of a seed, e.g. a thread,
+// or one seeded from system entropy if there is no seed.
+pub(super) fn seeded_rng(seed: Option<u64>, index: usize) -> StdRng {
+    match seed {
+        Some(seed) => {
+            StdRng::seed_from_u64(seed ^ (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+        }
+        None => StdRng::from_entropy(),
     }
 }
 
-// Undo any value clamping.
-pub(super) fn unclamp_value(value: Evaluation) -> Evaluation {
-    if value > BEST_EVAL - 100 {
-        BEST_EVAL
-    } else if value < WORST_EVAL + 100 {
-        WORST_EVAL
-    } else {
-        value
-    }
+pub(super) fn timeout_signal(dur: Duration) -> Arc<AtomicBool> {
+    let signal = Arc::new(AtomicBool::new(false));
+    signal_after(signal.clone(), dur);
+    signal
 }
 
 #[cfg(feature = "wasm-bindgen")]
-pub(super) fn timeout_signal(dur: Duration) -> Arc<AtomicBool> {
-    let signal = Arc::new(AtomicBool::new(false));
-    let signal2 = signal.clone();
+pub(super) fn signal_after(signal: Arc<AtomicBool>, dur: Duration) {
     Timeout::new(dur.as_millis() as u32, move || {
-        signal2.store(true, Ordering::Relaxed)
+        signal.store(true, Ordering::Relaxed)
     }).forget();
-    signal
 }
 
 #[cfg(not(feature = "wasm-bindgen"))]
-pub(super) fn timeout_signal(dur: Duration) -> Arc<AtomicBool> {
+pub(super) fn signal_after(signal: Arc<AtomicBool>, dur: Duration) {
     // Theoretically we could include an async runtime to do this and use
     // fewer threads, but the stdlib implementation is only a few lines...
-    let signal = Arc::new(AtomicBool::new(false));
-    let signal2 = signal.clone();
     spawn(move || {
         sleep(dur);
-        signal2.store(true, Ordering::Relaxed);
+        signal.store(true, Ordering::Relaxed);
     });
-    signal
 }
 
 // This exists to be wrapped in a mutex, because it didn't work when I tried a tuple.'
@@ -75,3 +66,22 @@ impl<M> ValueMove<M> {
         (self.value, self.m)
     }
 }
+
+// Little-endian readers for the on-disk formats.
+pub(super) fn read_u16<R: Read>(r: &mut R) -> Result<u16> {
+    let mut buf = [0; 2];
+    r.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+pub(super) fn read_u32<R: Read>(r: &mut R) -> Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub(super) fn read_u64<R: Read>(r: &mut R) -> Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
@@ -4,16 +4,26 @@
 //! the best guess move serially first, then parallelizes all other moves
 //! using rayon. This tries to reduce redundant computation at the expense of
 //! more board state clones and slightly more thread synchronization.
+//!
+//! The threads share one transposition table. It is only lock-free when
+//! created by `ParallelYbw::new_packed` for a `PackableMove`; otherwise each
+//! entry is behind a mutex, which threads can contend on.
 
 extern crate rayon;
 
 use super::super::interface::*;
+use super::iterative::Replacement;
+use super::ordering::MoveOrdering;
+use super::progress::{Progress, SearchProgress, TICK_NODES};
+use super::stats::SearchStats;
 use super::table::*;
+use super::time::{SearchLimits, TimeControl};
 use super::util::*;
 
 use rayon::prelude::*;
-use std::cmp::max;
-use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::cmp::{max, min};
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use instant::{Duration, Instant};
 
@@ -21,20 +31,34 @@ use instant::{Duration, Instant};
 #[derive(Clone, Copy)]
 pub struct YbwOptions {
     table_byte_size: usize,
+    strategy: Replacement,
     null_window_search: bool,
     step_increment: u8,
     max_quiescence_depth: u8,
+    delta_pruning: bool,
     serial_cutoff_depth: u8,
+    null_move_reduction: Option<u8>,
+    killer_moves: bool,
+    history_heuristic: bool,
+    countermove_heuristic: bool,
+    deterministic: bool,
 }
 
 impl YbwOptions {
     pub fn new() -> Self {
         YbwOptions {
             table_byte_size: 32_000_000,
+            strategy: Replacement::TwoTier,
             null_window_search: true,
             step_increment: 1,
             max_quiescence_depth: 0,
+            delta_pruning: false,
             serial_cutoff_depth: 1,
+            null_move_reduction: None,
+            killer_moves: true,
+            history_heuristic: true,
+            countermove_heuristic: true,
+            deterministic: false,
         }
     }
 }
@@ -52,6 +76,13 @@ impl YbwOptions {
         self
     }
 
+    /// What rules to use when choosing whether to overwrite the current value
+    /// in the transposition table.
+    pub fn with_replacement_strategy(mut self, strategy: Replacement) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
     /// Whether to add null-window searches to try to prune branches that are
     /// probably worse than those already found. Also known as principal
     /// variation search.
@@ -74,16 +105,82 @@ impl YbwOptions {
         self.max_quiescence_depth = depth;
         self
     }
+
+    /// Enable [delta
+    /// pruning](https://www.chessprogramming.org/Delta_Pruning) in
+    /// quiescence search, which skips noisy moves that cannot raise the
+    /// evaluation above the best value already found. The Game must
+    /// implement `delta_pruning_margin` for this to have any effect.
+    pub fn with_delta_pruning(mut self) -> Self {
+        self.delta_pruning = true;
+        self
+    }
+
+    /// Enable [null-move
+    /// pruning](https://www.chessprogramming.org/Null_Move_Pruning), which
+    /// cuts off positions where passing the turn is already too good for the
+    /// opponent to allow, according to a search reduced by an extra
+    /// `depth_reduction` plies (R). The Game must implement `null_move` for
+    /// this to have any effect.
+    pub fn with_null_move_pruning(mut self, depth_reduction: u8) -> Self {
+        self.null_move_reduction = Some(depth_reduction);
+        self
+    }
+
+    /// Whether to first try the moves that most recently caused a cutoff at
+    /// the same depth in other branches. Also known as the killer heuristic.
+    pub fn with_killer_moves(mut self, killers: bool) -> Self {
+        self.killer_moves = killers;
+        self
+    }
+
+    /// Whether to order moves by how often they caused cutoffs anywhere in
+    /// the search tree. The Game must implement `move_index` for this to
+    /// have any effect.
+    pub fn with_history_heuristic(mut self, history: bool) -> Self {
+        self.history_heuristic = history;
+        self
+    }
+
+    /// Whether to first try the move that most recently refuted the
+    /// opponent's previous move. The Game must implement `move_index` for
+    /// this to have any effect.
+    pub fn with_countermove_heuristic(mut self, countermoves: bool) -> Self {
+        self.countermove_heuristic = countermoves;
+        self
+    }
+
+    /// Search serially, so that the same position and limits always give
+    /// the same move and principal variation, e.g. to reproduce a bug.
+    /// Limit the search by depth or nodes rather than time for this to hold.
+    ///
+    /// This is not a parallel search: every node is searched on the calling
+    /// thread, so it is no faster than a single thread.
+    pub fn with_deterministic_search(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
 }
 
 pub struct ParallelYbw<E: Evaluator> {
-    max_depth: usize,
-    max_time: Duration,
+    limits: SearchLimits,
     timeout: Arc<AtomicBool>,
+    // Set from outside the search.
+    stop: Arc<AtomicBool>,
+    // Nodes explored by all threads, for the node limit.
+    search_nodes: AtomicU64,
+    progress: Mutex<Progress<<E::G as Game>::M>>,
     table: ConcurrentTable<<<E as Evaluator>::G as Game>::M>,
+    ordering: MoveOrdering<E::G>,
     //move_pool: MovePool<<E::G as Game>::M>,
     prev_value: Evaluation,
     eval: E,
+    // Moves the root may choose from. The root isn't stored in the table
+    // while any are skipped.
+    root_moves: RootMoves<<E::G as Game>::M>,
+    // Best move of the root from the last search, which other threads may
+    // evict from the table.
+    root_best_move: Mutex<Option<<E::G as Game>::M>>,
 
     opts: YbwOptions,
 
@@ -96,69 +193,159 @@ pub struct ParallelYbw<E: Evaluator> {
     // Nodes explored past this depth, and thus only useful for filling TT for
     // next choose_move.
     next_depth_nodes: u64,
+    // Nodes explored by the calling thread, then by each thread in the pool.
+    thread_nodes: Vec<AtomicU64>,
     // For computing the average branching factor.
-    total_generate_move_calls: u64,
-    total_generated_moves: u64,
-    table_hits: usize,
+    total_generate_move_calls: AtomicU64,
+    total_generated_moves: AtomicU64,
+    beta_cutoffs: AtomicU64,
+    first_move_cutoffs: AtomicU64,
+    table_hits: AtomicU64,
     pv: Vec<<E::G as Game>::M>,
     wall_time: Duration,
 }
 
 impl<E: Evaluator> ParallelYbw<E> {
+    /// Create a search whose threads lock each entry of their shared
+    /// transposition table with a mutex. See `new_packed` to avoid this.
     pub fn new(eval: E, opts: YbwOptions) -> ParallelYbw<E> {
-        let table = ConcurrentTable::new(opts.table_byte_size);
+        let table = ConcurrentTable::new(opts.table_byte_size, opts.strategy);
+        Self::from_table(eval, opts, table)
+    }
+
+    /// Like `new`, but the transposition table stores moves packed by
+    /// `PackableMove`, so that it holds more positions in the same memory
+    /// and the threads share it without locks.
+    pub fn new_packed(eval: E, opts: YbwOptions) -> ParallelYbw<E>
+    where
+        <E::G as Game>::M: PackableMove,
+    {
+        let table = ConcurrentTable::new_packed(opts.table_byte_size, opts.strategy);
+        Self::from_table(eval, opts, table)
+    }
+
+    fn from_table(
+        eval: E, opts: YbwOptions, table: ConcurrentTable<<E::G as Game>::M>,
+    ) -> ParallelYbw<E> {
+        let ordering = MoveOrdering::new(
+            opts.killer_moves,
+            opts.history_heuristic,
+            opts.countermove_heuristic,
+        );
         ParallelYbw {
-            max_depth: 100,
-            max_time: Duration::from_secs(5),
+            limits: SearchLimits::new().with_time(Duration::from_secs(5)),
             timeout: Arc::new(AtomicBool::new(false)),
+            stop: Arc::new(AtomicBool::new(false)),
+            search_nodes: AtomicU64::new(0),
+            progress: Mutex::new(Progress::new()),
             table,
+            ordering,
             //move_pool: MovePool::<_>::default(),
             prev_value: 0,
             opts,
             eval,
+            root_moves: RootMoves::All,
+            root_best_move: Mutex::new(None),
             actual_depth: 0,
             nodes_explored: Vec::new(),
             next_depth_nodes: 0,
-            total_generate_move_calls: 0,
-            total_generated_moves: 0,
-            table_hits: 0,
+            thread_nodes: (0..=rayon::current_num_threads()).map(|_| AtomicU64::new(0)).collect(),
+            total_generate_move_calls: AtomicU64::new(0),
+            total_generated_moves: AtomicU64::new(0),
+            beta_cutoffs: AtomicU64::new(0),
+            first_move_cutoffs: AtomicU64::new(0),
+            table_hits: AtomicU64::new(0),
             pv: Vec::new(),
             wall_time: Duration::default(),
         }
     }
 
-    /// Set the maximum depth to search. Disables the timeout.
+    /// Set the maximum depth to search. Disables the timeout, see
+    /// `set_limits` to combine them.
     /// This can be changed between moves while reusing the transposition table.
     pub fn set_max_depth(&mut self, depth: usize) {
-        self.max_depth = depth;
-        self.max_time = Duration::new(0, 0);
+        self.limits = SearchLimits::new().with_depth(depth);
     }
 
     /// Set the maximum time to compute the best move. When the timeout is
     /// hit, it returns the best move found of the previous full
     /// iteration. Unlimited max depth.
     pub fn set_timeout(&mut self, max_time: Duration) {
-        self.max_time = max_time;
-        self.max_depth = 100;
+        self.limits = SearchLimits::new().with_time(max_time);
+    }
+
+    /// Budget the time for each move from the state of the game clock,
+    /// which should be updated before each move. Unlimited max depth.
+    pub fn set_time_control(&mut self, clock: TimeControl) {
+        self.limits = SearchLimits::new().with_time_control(clock);
+    }
+
+    /// Set any combination of limits, replacing those set before.
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    /// Call the callback with the progress of the search after each
+    /// completed iteration, and during iterations that take longer than
+    /// `interval`.
+    pub fn set_progress_callback<F>(&mut self, interval: Duration, callback: F)
+    where
+        F: FnMut(&SearchProgress<<E::G as Game>::M>) + Send + 'static,
+    {
+        self.progress.get_mut().unwrap().set_callback(interval, Box::new(callback));
     }
 
-    /// Return a human-readable summary of the last move generation.
-    pub fn stats(&self) -> String {
-        let total_nodes_explored: u64 = self.nodes_explored.iter().sum();
-        let mean_branching_factor =
-            self.total_generated_moves as f64 / self.total_generate_move_calls as f64;
-        let effective_branching_factor = (*self.nodes_explored.last().unwrap_or(&0) as f64)
-            .powf((self.actual_depth as f64 + 1.0).recip());
-        let throughput =
-            (total_nodes_explored + self.next_depth_nodes) as f64 / self.wall_time.as_secs_f64();
-        format!("Explored {} nodes to depth {}. MBF={:.1} EBF={:.1}\nPartial exploration of next depth hit {} nodes.\n{} transposition table hits.\n{} nodes/sec",
-		total_nodes_explored, self.actual_depth, mean_branching_factor, effective_branching_factor,
-		self.next_depth_nodes, self.table_hits, throughput as usize)
+    /// Restrict the moves of the root that the following searches may
+    /// choose from, until replaced with `RootMoves::All`. See
+    /// `IterativeSearch::set_root_moves`.
+    pub fn set_root_moves(&mut self, root_moves: RootMoves<<E::G as Game>::M>) {
+        self.root_moves = root_moves;
+    }
+
+    /// Return statistics about the last move generation.
+    pub fn stats(&self) -> SearchStats {
+        SearchStats {
+            depth: self.actual_depth,
+            nodes_per_depth: self.nodes_explored.clone(),
+            partial_nodes: self.next_depth_nodes,
+            thread_nodes: self.thread_nodes.iter().map(|n| n.load(Ordering::Relaxed)).collect(),
+            table_hits: self.table_hits.load(Ordering::Relaxed),
+            generate_move_calls: self.total_generate_move_calls.load(Ordering::Relaxed),
+            generated_moves: self.total_generated_moves.load(Ordering::Relaxed),
+            beta_cutoffs: self.beta_cutoffs.load(Ordering::Relaxed),
+            first_move_cutoffs: self.first_move_cutoffs.load(Ordering::Relaxed),
+            zero_window_searches: None,
+            wall_time: self.wall_time,
+        }
+    }
+
+    /// Save the transposition table, so that a later search can resume the
+    /// analysis after `read_table`.
+    pub fn write_table<W: Write>(&self, w: &mut W) -> io::Result<()>
+    where
+        <E::G as Game>::M: Copy + SerializableMove,
+    {
+        self.table.write_to(w)
+    }
+
+    /// Load a table saved by `write_table`, possibly with a different table
+    /// size. Entries already in the table are kept where there's room.
+    pub fn read_table<R: Read>(&mut self, r: &mut R) -> io::Result<()>
+    where
+        <E::G as Game>::M: Copy + SerializableMove,
+    {
+        self.table.read_from(r)
     }
 
     #[doc(hidden)]
     pub fn root_value(&self) -> Evaluation {
-        unclamp_value(self.prev_value)
+        self.prev_value
+    }
+
+    /// The result of the last search for the player to move: a forced win
+    /// or loss, or else the evaluation of the root.
+    pub fn root_score(&self) -> Score {
+        Score::from(self.prev_value)
     }
 
     /// Return what the engine considered to be the best sequence of moves
@@ -167,98 +354,226 @@ impl<E: Evaluator> ParallelYbw<E> {
         &self.pv[..]
     }
 
-    // Negamax only among noisy moves.
+    // Negamax only among noisy moves, where the player to move may also
+    // stand pat and accept the static evaluation.
     fn noisy_negamax(
-        &self, s: &mut <E::G as Game>::S, depth: u8, mut alpha: Evaluation, beta: Evaluation,
+        &self, s: &mut <E::G as Game>::S, depth: u8, ply: usize, mut alpha: Evaluation,
+        mut beta: Evaluation,
     ) -> Option<Evaluation>
     where
-        <E::G as Game>::M: Copy,
+        <E::G as Game>::S: Zobrist,
+        <E::G as Game>::M: Copy + Eq,
     {
-        if self.timeout.load(Ordering::Relaxed) {
+        if self.stopped() {
             return None;
         }
         if let Some(winner) = E::G::get_winner(s) {
-            return Some(winner.evaluate());
+            return Some(winner.evaluate_at_ply(ply));
         }
         if depth == 0 {
             return Some(self.eval.evaluate(s));
         }
 
+        let alpha_orig = alpha;
+        let hash = s.zobrist_hash();
+        let mut good_move = None;
+        if let Some(value) = self.table.check(hash, 0, ply, &mut good_move, &mut alpha, &mut beta) {
+            self.table_hits.fetch_add(1, Ordering::Relaxed);
+            return Some(value);
+        }
+
+        // The static evaluation is a lower bound, as the player to move
+        // doesn't have to make a noisy move.
+        let stand_pat = self.eval.evaluate(s);
+        if stand_pat >= beta {
+            return Some(stand_pat);
+        }
+        alpha = max(alpha, stand_pat);
+
         //let mut moves = self.move_pool.alloc();
         let mut moves = Vec::new();
         E::G::generate_noisy_moves(s, &mut moves);
-        if moves.is_empty() {
-            // Only quiet moves remain, return leaf evaluation.
-            //self.move_pool.free(moves);
-            return Some(self.eval.evaluate(s));
+        if let Some(good) = good_move {
+            // Rearrange so predicted good move is first.
+            if let Some(i) = moves.iter().position(|&m| m == good) {
+                moves.swap(0, i);
+            }
         }
 
-        let mut best = WORST_EVAL;
-        for m in moves.iter() {
+        let mut best = stand_pat;
+        let mut best_move = None;
+        for &m in moves.iter() {
+            if self.opts.delta_pruning {
+                if let Some(margin) = E::G::delta_pruning_margin(s, &m) {
+                    // Keep the result an upper bound of the skipped move.
+                    let bound = stand_pat.saturating_add(margin);
+                    if bound <= alpha {
+                        best = max(best, bound);
+                        continue;
+                    }
+                }
+            }
             m.apply(s);
-            let value = -self.noisy_negamax(s, depth - 1, -beta, -alpha)?;
+            let value = -self.noisy_negamax(s, depth - 1, ply + 1, -beta, -alpha)?;
             m.undo(s);
-            best = max(best, value);
+            if value > best {
+                best = value;
+                best_move = Some(m);
+            }
             alpha = max(alpha, value);
             if alpha >= beta {
                 break;
             }
         }
         //self.move_pool.free(moves);
+        // Standing pat has no move to remember.
+        if let Some(best_move) = best_move {
+            self.table.concurrent_update(hash, alpha_orig, beta, 0, ply, best, best_move);
+        }
         Some(best)
     }
 
+    // Whether the position is still at least beta after passing the turn,
+    // according to a reduced-depth null-window search. Returns None if it
+    // hits the timeout.
+    fn null_move_cutoff(
+        &self, s: &mut <E::G as Game>::S, depth: u8, beta: Evaluation, ply: usize,
+        prev_move: Option<<E::G as Game>::M>,
+    ) -> Option<bool>
+    where
+        <E::G as Game>::S: Clone + Zobrist + Send + Sync,
+        <E::G as Game>::M: Copy + Eq + Send + Sync,
+        E: Sync,
+    {
+        let reduction = match self.opts.null_move_reduction {
+            Some(reduction) if depth > reduction => reduction,
+            _ => return Some(false),
+        };
+        // Null moves can't prove wins or losses.
+        if !(WORST_EVAL + WIN_DISTANCE..=BEST_EVAL - WIN_DISTANCE).contains(&beta) {
+            return Some(false);
+        }
+        // Never pass at the root, which needs a real move, or twice in a row.
+        let null_move = match E::G::null_move(s) {
+            Some(m) if ply > 0 && prev_move != Some(m) => m,
+            _ => return Some(false),
+        };
+        null_move.apply(s);
+        let value =
+            -self.negamax(s, depth - 1 - reduction, -beta, -beta + 1, ply + 1, Some(null_move))?;
+        null_move.undo(s);
+        Some(value >= beta)
+    }
+
+    fn stopped(&self) -> bool {
+        self.timeout.load(Ordering::Relaxed)
+            || self.stop.load(Ordering::Relaxed)
+            || self.search_nodes.load(Ordering::Relaxed) >= self.limits.node_limit()
+    }
+
     // Recursively compute negamax on the game state. Returns None if it hits the timeout.
     fn negamax(
         &self, s: &mut <E::G as Game>::S, depth: u8, mut alpha: Evaluation, mut beta: Evaluation,
+        ply: usize, prev_move: Option<<E::G as Game>::M>,
     ) -> Option<Evaluation>
     where
         <E::G as Game>::S: Clone + Zobrist + Send + Sync,
         <E::G as Game>::M: Copy + Eq + Send + Sync,
         E: Sync,
     {
-        if self.timeout.load(Ordering::Relaxed) {
+        if self.stopped() {
             return None;
         }
 
-        //self.next_depth_nodes += 1;
+        // Threads outside the pool count as the calling thread.
+        let thread = rayon::current_thread_index().map_or(0, |i| i + 1);
+        if let Some(thread_nodes) = self.thread_nodes.get(thread) {
+            thread_nodes.fetch_add(1, Ordering::Relaxed);
+        }
+        let nodes = self.search_nodes.fetch_add(1, Ordering::Relaxed) + 1;
+        if nodes.is_multiple_of(TICK_NODES) {
+            // Skip the report if another thread is already making one.
+            if let Ok(mut progress) = self.progress.try_lock() {
+                progress.tick(self.search_nodes.load(Ordering::Relaxed));
+            }
+        }
 
         if depth == 0 {
             // Evaluate quiescence search on leaf nodes.
             // Will just return the node's evaluation if quiescence search is disabled.
-            return self.noisy_negamax(s, self.opts.max_quiescence_depth, alpha, beta);
+            return self.noisy_negamax(s, self.opts.max_quiescence_depth, ply, alpha, beta);
         }
         if let Some(winner) = E::G::get_winner(s) {
-            return Some(winner.evaluate());
+            return Some(winner.evaluate_at_ply(ply));
+        }
+        // Mate-distance pruning: no result here can beat a win already found
+        // closer to the root. The root is always searched, so its move is
+        // stored.
+        if ply > 0 {
+            alpha = max(alpha, WORST_EVAL + ply as Evaluation);
+            beta = min(beta, BEST_EVAL - ply as Evaluation - 1);
+            if alpha >= beta {
+                return Some(alpha);
+            }
         }
 
         let alpha_orig = alpha;
         let hash = s.zobrist_hash();
         let mut good_move = None;
-        if let Some(value) = self.table.check(hash, depth, &mut good_move, &mut alpha, &mut beta) {
+        let filtering = ply == 0 && self.root_moves != RootMoves::All;
+        if ply == 0 {
+            *self.root_best_move.lock().unwrap() = None;
+        }
+        if filtering {
+            // The value in the table may come from other moves of the root,
+            // so only use the move.
+            good_move = self.table.lookup(hash).and_then(|entry| entry.best_move);
+        } else if let Some(value) =
+            self.table.check(hash, depth, ply, &mut good_move, &mut alpha, &mut beta)
+        {
+            self.table_hits.fetch_add(1, Ordering::Relaxed);
+            if ply == 0 {
+                *self.root_best_move.lock().unwrap() = good_move;
+            }
             return Some(value);
         }
+        if self.null_move_cutoff(s, depth, beta, ply, prev_move)? {
+            return Some(beta);
+        }
 
         //let mut moves = self.move_pool.alloc();
         let mut moves = Vec::new();
         E::G::generate_moves(s, &mut moves);
-        //self.total_generate_move_calls += 1;
-        //self.total_generated_moves += moves.len() as u64;
+        if filtering {
+            moves.retain(|m| self.root_moves.allows(m));
+        }
+        self.total_generate_move_calls.fetch_add(1, Ordering::Relaxed);
+        self.total_generated_moves.fetch_add(moves.len() as u64, Ordering::Relaxed);
         if moves.is_empty() {
             //self.move_pool.free(moves);
-            return Some(WORST_EVAL);
+            return Some(WORST_EVAL + ply as Evaluation);
+        }
+        let mut first_unordered = 0;
+        if let Some(good) = good_move {
+            // Rearrange so predicted good move is first.
+            if let Some(i) = moves.iter().position(|&m| m == good) {
+                moves.swap(0, i);
+                first_unordered = 1;
+            }
         }
-        let first_move = good_move.unwrap_or(moves[0]);
+        self.ordering.order_moves(&mut moves[first_unordered..], ply, prev_move);
+        let first_move = moves[0];
 
         // Evaluate first move serially.
         first_move.apply(s);
-        let initial_value = -self.negamax(s, depth - 1, -beta, -alpha)?;
+        let initial_value =
+            -self.negamax(s, depth - 1, -beta, -alpha, ply + 1, Some(first_move))?;
         first_move.undo(s);
         alpha = max(alpha, initial_value);
         let (best, best_move) = if alpha >= beta {
             // Skip search
             (initial_value, first_move)
-        } else if self.opts.serial_cutoff_depth >= depth {
+        } else if self.opts.deterministic || self.opts.serial_cutoff_depth >= depth {
             // Serial search
             let mut best = initial_value;
             let mut best_move = first_move;
@@ -269,15 +584,16 @@ impl<E: Evaluator> ParallelYbw<E> {
                 }
                 m.apply(s);
                 let value = if null_window {
-                    let probe = -self.negamax(s, depth - 1, -alpha - 1, -alpha)?;
+                    let probe =
+                        -self.negamax(s, depth - 1, -alpha - 1, -alpha, ply + 1, Some(m))?;
                     if probe > alpha && probe < beta {
                         // Full search fallback.
-                        -self.negamax(s, depth - 1, -beta, -probe)?
+                        -self.negamax(s, depth - 1, -beta, -probe, ply + 1, Some(m))?
                     } else {
                         probe
                     }
                 } else {
-                    -self.negamax(s, depth - 1, -beta, -alpha)?
+                    -self.negamax(s, depth - 1, -beta, -alpha, ply + 1, Some(m))?
                 };
                 m.undo(s);
                 if value > best {
@@ -310,20 +626,26 @@ impl<E: Evaluator> ParallelYbw<E> {
                 m.apply(&mut state);
                 let value = if self.opts.null_window_search && initial_alpha > alpha_orig {
                     // TODO: send reference to alpha as neg_beta to children.
-                    let probe =
-                        -self.negamax(&mut state, depth - 1, -initial_alpha - 1, -initial_alpha)?;
+                    let probe = -self.negamax(
+                        &mut state,
+                        depth - 1,
+                        -initial_alpha - 1,
+                        -initial_alpha,
+                        ply + 1,
+                        Some(m),
+                    )?;
                     if probe > initial_alpha && probe < beta {
                         // Check again that we're not cancelled.
                         if alpha.load(Ordering::SeqCst) >= beta {
                             return None;
                         }
                         // Full search fallback.
-                        -self.negamax(&mut state, depth - 1, -beta, -probe)?
+                        -self.negamax(&mut state, depth - 1, -beta, -probe, ply + 1, Some(m))?
                     } else {
                         probe
                     }
                 } else {
-                    -self.negamax(&mut state, depth - 1, -beta, -initial_alpha)?
+                    -self.negamax(&mut state, depth - 1, -beta, -initial_alpha, ply + 1, Some(m))?
                 };
 
                 alpha.fetch_max(value, Ordering::SeqCst);
@@ -333,16 +655,28 @@ impl<E: Evaluator> ParallelYbw<E> {
             });
             if result.is_none() {
                 // Check for timeout.
-                if self.timeout.load(Ordering::Relaxed) {
+                if self.stopped() {
                     return None;
                 }
             }
             best_move.into_inner().unwrap().into_inner()
         };
+        if best >= beta {
+            self.beta_cutoffs.fetch_add(1, Ordering::Relaxed);
+            if best_move == first_move {
+                self.first_move_cutoffs.fetch_add(1, Ordering::Relaxed);
+            }
+            self.ordering.record_cutoff(best_move, depth, ply, prev_move);
+        }
 
-        self.table.concurrent_update(hash, alpha_orig, beta, depth, best, best_move);
+        if ply == 0 {
+            *self.root_best_move.lock().unwrap() = Some(best_move);
+        }
+        if !filtering {
+            self.table.concurrent_update(hash, alpha_orig, beta, depth, ply, best, best_move);
+        }
         //self.move_pool.free(moves);
-        Some(clamp_value(best))
+        Some(best)
     }
 }
 
@@ -354,41 +688,71 @@ where
 {
     fn choose_move(&mut self, s: &<E::G as Game>::S) -> Option<<E::G as Game>::M> {
         self.table.advance_generation();
+        self.ordering.new_search();
         // Reset stats.
         self.nodes_explored.clear();
         self.next_depth_nodes = 0;
-        self.total_generate_move_calls = 0;
-        self.total_generated_moves = 0;
+        for thread_nodes in self.thread_nodes.iter() {
+            thread_nodes.store(0, Ordering::Relaxed);
+        }
+        self.total_generate_move_calls.store(0, Ordering::Relaxed);
+        self.total_generated_moves.store(0, Ordering::Relaxed);
+        self.beta_cutoffs.store(0, Ordering::Relaxed);
+        self.first_move_cutoffs.store(0, Ordering::Relaxed);
         self.actual_depth = 0;
-        self.table_hits = 0;
+        self.table_hits.store(0, Ordering::Relaxed);
         let start_time = Instant::now();
         // Start timer if configured.
-        self.timeout = if self.max_time == Duration::new(0, 0) {
-            Arc::new(AtomicBool::new(false))
-        } else {
-            timeout_signal(self.max_time)
-        };
+        let (timeout, mut manager) = self.limits.start_timer();
+        self.timeout = timeout;
+        self.stop = self.limits.stop_token();
+        self.search_nodes.store(0, Ordering::Relaxed);
+        self.progress.get_mut().unwrap().start();
 
-        let root_hash = s.zobrist_hash();
         let mut s_clone = s.clone();
         let mut best_move = None;
-
-        let mut depth = self.max_depth as u8 % self.opts.step_increment;
-        while depth <= self.max_depth as u8 {
-            if self.negamax(&mut s_clone, depth + 1, WORST_EVAL, BEST_EVAL).is_none() {
+        let mut counted_nodes = 0;
+
+        let max_depth = self.limits.max_depth() as u8;
+        let mut depth = max_depth % self.opts.step_increment;
+        while depth <= max_depth {
+            let value = match self.negamax(&mut s_clone, depth + 1, WORST_EVAL, BEST_EVAL, 0, None)
+            {
+                Some(value) => value,
                 // Timeout. Return the best move from the previous depth.
-                break;
-            }
-            let entry = self.table.lookup(root_hash).unwrap();
-            best_move = entry.best_move;
+                None => break,
+            };
+            let root_result = self.root_best_move.get_mut().unwrap().zip(Some(value));
+            let (root_move, root_value) = match root_result {
+                Some(result) => result,
+                // No moves to choose from.
+                None => break,
+            };
+            let best_move_changed = best_move.is_some() && best_move != Some(root_move);
+            best_move = Some(root_move);
 
             self.actual_depth = max(self.actual_depth, depth);
-            self.nodes_explored.push(self.next_depth_nodes);
-            self.prev_value = entry.value;
-            self.next_depth_nodes = 0;
+            self.nodes_explored.push(*self.search_nodes.get_mut() - counted_nodes);
+            counted_nodes = *self.search_nodes.get_mut();
+            self.prev_value = root_value;
             depth += self.opts.step_increment;
-            self.table.populate_pv(&mut self.pv, &mut s_clone, depth + 1);
+            root_move.apply(&mut s_clone);
+            self.table.populate_pv(&mut self.pv, &mut s_clone, depth);
+            root_move.undo(&mut s_clone);
+            self.pv.insert(0, root_move);
+            self.progress.get_mut().unwrap().iteration(
+                self.actual_depth,
+                self.prev_value,
+                *self.search_nodes.get_mut(),
+                &self.pv,
+            );
+            if let Some(manager) = manager.as_mut() {
+                if !manager.next_iteration(&self.nodes_explored, best_move_changed) {
+                    break;
+                }
+            }
         }
+        self.next_depth_nodes = *self.search_nodes.get_mut() - counted_nodes;
         self.wall_time = start_time.elapsed();
         best_move
     }
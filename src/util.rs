@@ -1,7 +1,8 @@
 //! Utility functions for testing, and tests.
 
 use super::interface;
-use super::interface::{Game, Move};
+use super::interface::{Game, Move, Zobrist};
+use std::collections::HashMap;
 use std::default::Default;
 use instant::Instant;
 
@@ -15,11 +16,45 @@ where
     G::S: Default,
     S1: interface::Strategy<G>,
     S2: interface::Strategy<G>,
+{
+    play::<G>([s1, s2], |_| false)
+}
+
+/// Play a complete, new game like `battle_royale`, but adjudicate it as a
+/// draw when any position occurs for the `occurrences`th time, e.g. 3 for
+/// threefold repetition.
+pub fn battle_royale_with_repetition<G, S1, S2>(
+    s1: &mut S1, s2: &mut S2, occurrences: usize,
+) -> Option<usize>
+where
+    G: interface::Game,
+    G::S: Default + Zobrist,
+    S1: interface::Strategy<G>,
+    S2: interface::Strategy<G>,
+{
+    let mut history = HashMap::new();
+    play::<G>([s1, s2], |state| {
+        let count = history.entry(state.zobrist_hash()).or_insert(0);
+        *count += 1;
+        *count >= occurrences
+    })
+}
+
+// Plays the game until there's a winner or the adjudicator, which is shown
+// every position, declares a draw.
+fn play<G: interface::Game>(
+    mut strategies: [&mut dyn interface::Strategy<G>; 2],
+    mut adjudicate_draw: impl FnMut(&G::S) -> bool,
+) -> Option<usize>
+where
+    G::S: Default,
 {
     let mut state = G::S::default();
-    let mut strategies: [&mut dyn interface::Strategy<G>; 2] = [s1, s2];
     let mut s = 0;
     while G::get_winner(&state).is_none() {
+        if adjudicate_draw(&state) {
+            return None;
+        }
         let strategy = &mut strategies[s];
         match strategy.choose_move(&state) {
             Some(m) => m.apply(&mut state),
@@ -46,7 +81,7 @@ impl<M> Default for MovePool<M> {
 
 impl<M> MovePool<M> {
     pub(crate) fn alloc(&mut self) -> Vec<M> {
-        self.pool.pop().unwrap_or_else(Vec::new)
+        self.pool.pop().unwrap_or_default()
     }
 
     pub(crate) fn free(&mut self, mut vec: Vec<M>) {
@@ -0,0 +1,140 @@
+// Opening books built from recorded games or from analysis should be played
+// by BookStrategy while in book, and survive a round trip to disk.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+use std::io::ErrorKind;
+
+// Play the columns from the initial board.
+fn play(cols: &[u8]) -> Vec<connect4::Place> {
+    let mut b = connect4::Board::default();
+    let mut moves = Vec::new();
+    for &col in cols {
+        let mut legal = Vec::new();
+        connect4::Game::generate_moves(&b, &mut legal);
+        let m = legal[col as usize];
+        m.apply(&mut b);
+        moves.push(m);
+    }
+    moves
+}
+
+#[test]
+fn recorded_games() {
+    let start = connect4::Board::default();
+    let mut builder = BookBuilder::<connect4::Game>::new(2);
+    builder.add_game(&start, &play(&[3, 3, 2, 4]));
+    builder.add_game(&start, &play(&[3, 2, 2]));
+    builder.add_game(&start, &play(&[2, 3]));
+    let book = builder.build();
+    // The initial board, and the boards after the first moves.
+    assert_eq!(3, book.len());
+
+    let center = play(&[3])[0];
+    let mut root_moves = book.probe(&start);
+    root_moves.sort_by_key(|&(_, weight)| weight);
+    assert_eq!(vec![(play(&[2])[0], 1), (center, 2)], root_moves);
+
+    // Out of book after two plies.
+    let mut b = start.clone();
+    for m in play(&[3, 3]) {
+        m.apply(&mut b);
+    }
+    assert!(book.probe(&b).is_empty());
+
+    let mut strategy = BookStrategy::new(book, strategies::random::Random::new());
+    for _ in 0..10 {
+        let m = strategy.choose_move(&start).unwrap();
+        assert!(m == center || m == play(&[2])[0]);
+    }
+    // Falls back to the inner strategy.
+    assert!(strategy.choose_move(&b).is_some());
+}
+
+#[test]
+fn analysis_round_trip() {
+    let start = connect4::Board::default();
+    let mut search = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    search.set_max_depth(4);
+    let mut builder = BookBuilder::<connect4::Game>::new(2);
+    builder.add_analysis(&start, &mut search);
+    let book = builder.build();
+    // The initial board and its seven children.
+    assert_eq!(8, book.len());
+    let root_moves = book.probe(&start);
+    assert_eq!(1, root_moves.len());
+    assert_eq!(search.choose_move(&start), Some(root_moves[0].0));
+
+    let mut bytes = Vec::new();
+    book.write_to(&mut bytes).unwrap();
+    let loaded = OpeningBook::<connect4::Game>::read_from(&mut &bytes[..]).unwrap();
+    assert_eq!(book.len(), loaded.len());
+    let mut moves = Vec::new();
+    connect4::Game::generate_moves(&start, &mut moves);
+    for m in moves {
+        let mut b = start.clone();
+        m.apply(&mut b);
+        assert_eq!(book.probe(&b), loaded.probe(&b));
+    }
+    let mut rewritten = Vec::new();
+    loaded.write_to(&mut rewritten).unwrap();
+    assert_eq!(bytes, rewritten);
+
+    assert!(OpeningBook::<connect4::Game>::read_from(&mut &bytes[1..]).is_err());
+}
+
+// A game with a single position with more moves than the book format holds.
+struct Wide;
+
+#[derive(Clone)]
+struct Root;
+
+impl Zobrist for Root {
+    fn zobrist_hash(&self) -> u64 {
+        0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Pick(u32);
+
+impl minimax::Move for Pick {
+    type G = Wide;
+    fn apply(&self, _: &mut Root) {}
+    fn undo(&self, _: &mut Root) {}
+}
+
+impl minimax::Game for Wide {
+    type S = Root;
+    type M = Pick;
+
+    fn generate_moves(_: &Root, moves: &mut Vec<Pick>) {
+        moves.extend((0..70_000).map(Pick));
+    }
+
+    fn get_winner(_: &Root) -> Option<Winner> {
+        None
+    }
+}
+
+#[test]
+fn large_books() {
+    // Saturated weights still add up.
+    let mut book = OpeningBook::<Wide>::new();
+    book.add_move(&Root, Pick(0), u32::MAX);
+    book.add_move(&Root, Pick(1), u32::MAX);
+    book.add_move(&Root, Pick(1), 1);
+    assert_eq!(vec![(Pick(0), u32::MAX), (Pick(1), u32::MAX)], book.probe(&Root));
+    let mut strategy = BookStrategy::new(book, strategies::random::Random::new());
+    strategy.set_seed(1);
+    assert!(strategy.choose_move(&Root).is_some());
+
+    // Moves past the first 2^16 can't be written.
+    let mut book = OpeningBook::<Wide>::new();
+    book.add_move(&Root, Pick(70_000 - 1), 1);
+    let err = book.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(ErrorKind::InvalidInput, err.kind());
+}
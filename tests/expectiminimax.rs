@@ -0,0 +1,170 @@
+// Star1 and Star2 pruning should never change the value of the root, only
+// how much of the tree is explored. This file creates random game trees with
+// alternating player and chance nodes, and checks that all pruning
+// strategies agree with a plain expectiminimax without any pruning.
+
+extern crate minimax;
+extern crate rand;
+
+use minimax::*;
+use rand::Rng;
+use std::cmp::max;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+const MAX_PLIES: usize = 9;
+const EVAL_BOUND: Evaluation = 100;
+
+// The state is the path of hashes from the root. Player nodes are at even
+// plies, chance nodes at odd plies.
+#[derive(Clone)]
+struct Path(Vec<u64>);
+
+impl Path {
+    fn hash(&self) -> u64 {
+        *self.0.last().unwrap()
+    }
+
+    fn is_chance(&self) -> bool {
+        self.0.len().is_multiple_of(2)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Branch(u8);
+
+impl minimax::Move for Branch {
+    type G = RandomTree;
+    fn apply(&self, path: &mut Path) {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(path.hash());
+        hasher.write_u8(self.0);
+        path.0.push(hasher.finish());
+    }
+    fn undo(&self, path: &mut Path) {
+        path.0.pop();
+    }
+}
+
+struct RandomTree;
+
+impl minimax::Game for RandomTree {
+    type S = Path;
+    type M = Branch;
+
+    fn generate_moves(path: &Path, moves: &mut Vec<Branch>) {
+        for i in 0..1 + path.hash() % 3 {
+            moves.push(Branch(i as u8));
+        }
+    }
+
+    fn get_winner(path: &Path) -> Option<Winner> {
+        if path.is_chance() && path.hash().is_multiple_of(13) {
+            Some(Winner::PlayerJustMoved)
+        } else if path.0.len() >= MAX_PLIES {
+            Some(Winner::Draw)
+        } else {
+            None
+        }
+    }
+}
+
+impl minimax::ChanceGame for RandomTree {
+    fn generate_outcomes(path: &Path, outcomes: &mut Vec<(Branch, f64)>) {
+        if !path.is_chance() {
+            return;
+        }
+        let n = 1 + path.hash() % 3;
+        let weights: Vec<u64> = (0..n).map(|i| 1 + (path.hash() >> (8 * i)) % 7).collect();
+        let total: u64 = weights.iter().sum();
+        for (i, w) in weights.iter().enumerate() {
+            outcomes.push((Branch(i as u8), *w as f64 / total as f64));
+        }
+    }
+}
+
+struct RandomEvaluator;
+
+impl minimax::Evaluator for RandomEvaluator {
+    type G = RandomTree;
+    fn evaluate(&self, path: &Path) -> Evaluation {
+        ((path.hash() >> 32) % (2 * EVAL_BOUND as u64 + 1)) as Evaluation - EVAL_BOUND
+    }
+}
+
+// Expectiminimax without any pruning, with the same rounding at chance nodes.
+// Wins and losses count their distance from the root until they're averaged
+// at a chance node.
+fn plain_expectiminimax(path: &mut Path, depth: usize, ply: usize) -> Evaluation {
+    if let Some(winner) = RandomTree::get_winner(path) {
+        return winner.evaluate_at_ply(ply);
+    }
+    if depth == 0 {
+        return RandomEvaluator.evaluate(path);
+    }
+    let mut outcomes = Vec::new();
+    RandomTree::generate_outcomes(path, &mut outcomes);
+    if !outcomes.is_empty() {
+        let mut sum = 0.0;
+        for (m, p) in outcomes {
+            m.apply(path);
+            let value = plain_expectiminimax(path, depth, ply + 1);
+            sum += p * value.clamp(-EVAL_BOUND, EVAL_BOUND) as f64;
+            m.undo(path);
+        }
+        return sum.round() as Evaluation;
+    }
+    let mut moves = Vec::new();
+    RandomTree::generate_moves(path, &mut moves);
+    let mut best = WORST_EVAL;
+    for m in moves {
+        m.apply(path);
+        best = max(best, -plain_expectiminimax(path, depth - 1, ply + 1));
+        m.undo(path);
+    }
+    best
+}
+
+#[test]
+fn compare_plain_expectiminimax() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        let mut path = Path(vec![rng.gen()]);
+        if RandomTree::get_winner(&path).is_some() {
+            continue;
+        }
+        for max_depth in 0..4 {
+            // Find the expected root value, including the root move.
+            let mut moves = Vec::new();
+            RandomTree::generate_moves(&path, &mut moves);
+            let value = moves
+                .iter()
+                .map(|m| {
+                    m.apply(&mut path);
+                    let value = -plain_expectiminimax(&mut path, max_depth, 1);
+                    m.undo(&mut path);
+                    value
+                })
+                .max()
+                .unwrap();
+
+            for pruning in [StarPruning::None, StarPruning::Star1, StarPruning::Star2] {
+                let mut search = Expectiminimax::new(
+                    RandomEvaluator,
+                    ExpectiminimaxOptions::new().with_pruning(pruning).with_eval_bound(EVAL_BOUND),
+                );
+                search.set_max_depth(max_depth);
+                search.choose_move(&path).unwrap();
+                assert_eq!(
+                    value,
+                    search.root_value(),
+                    "search depth={} pruning={:?} root={:x}",
+                    max_depth,
+                    pruning,
+                    path.hash()
+                );
+                assert_eq!(Score::from(value), search.root_score());
+            }
+        }
+    }
+}
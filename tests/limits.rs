@@ -0,0 +1,61 @@
+// Searches should stop at whichever of their limits they hit first, and
+// return the best move from the last completed iteration.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+
+// Set the token from another thread after a while, and check that the search
+// returns a move. Without the token, the search would never stop, so the
+// bound on its time is loose.
+fn stop_from_ui<S: Strategy<connect4::Game>>(search: &mut S, set_limits: fn(&mut S, SearchLimits)) {
+    let stop = Arc::new(AtomicBool::new(false));
+    set_limits(search, SearchLimits::new().with_stop_token(stop.clone()));
+    let stop2 = stop.clone();
+    spawn(move || {
+        sleep(Duration::from_millis(100));
+        stop2.store(true, Ordering::Relaxed);
+    });
+    let start = Instant::now();
+    assert!(search.choose_move(&connect4::Board::default()).is_some());
+    assert!(stop.load(Ordering::Relaxed));
+    assert!(start.elapsed() < Duration::from_secs(10), "{:?}", start.elapsed());
+}
+
+#[test]
+fn stop_token() {
+    let mut iterative = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    stop_from_ui(&mut iterative, IterativeSearch::set_limits);
+    let mut mtdf = MtdfSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    stop_from_ui(&mut mtdf, MtdfSearch::set_limits);
+    let mut lazy_smp = LazySmp::new(connect4::BasicEvaluator, LazySmpOptions::new());
+    stop_from_ui(&mut lazy_smp, LazySmp::set_limits);
+    let mut ybw = ParallelYbw::new(connect4::BasicEvaluator, YbwOptions::new());
+    stop_from_ui(&mut ybw, ParallelYbw::set_limits);
+}
+
+#[test]
+fn node_limit() {
+    let b = connect4::Board::default();
+    let mut search = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    search.set_limits(SearchLimits::new().with_nodes(5000));
+    assert!(search.choose_move(&b).is_some());
+    let nodes = search.stats().total_nodes();
+    assert!(nodes <= 5000 && nodes > 1000, "{}", search.stats());
+
+    // The other limits still apply.
+    search.set_limits(SearchLimits::new().with_nodes(1_000_000).with_depth(3));
+    search.choose_move(&b).unwrap();
+    assert_eq!(3, search.stats().depth);
+    search
+        .set_limits(SearchLimits::new().with_nodes(u64::MAX).with_time(Duration::from_millis(100)));
+    let start = Instant::now();
+    search.choose_move(&b).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(10), "{:?}", start.elapsed());
+}
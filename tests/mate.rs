@@ -0,0 +1,60 @@
+// Forced wins and losses should be reported with their distance in plies
+// from the root, and every iterative strategy should agree on it.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+
+// Play the given columns from an empty board.
+fn board(cols: &[usize]) -> connect4::Board {
+    let mut b = connect4::Board::default();
+    for &col in cols {
+        let mut moves = Vec::new();
+        connect4::Game::generate_moves(&b, &mut moves);
+        moves[col].apply(&mut b);
+    }
+    b
+}
+
+fn scores(b: &connect4::Board) -> Vec<Score> {
+    let mut iterative = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    iterative.set_max_depth(6);
+    iterative.choose_move(b).unwrap();
+
+    let mut mtdf = MtdfSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    mtdf.set_max_depth(6);
+    mtdf.choose_move(b).unwrap();
+
+    let mut lazy_smp =
+        LazySmp::new(connect4::BasicEvaluator, LazySmpOptions::new().with_num_threads(3));
+    lazy_smp.set_max_depth(6);
+    lazy_smp.choose_move(b).unwrap();
+
+    let mut ybw = ParallelYbw::new(connect4::BasicEvaluator, YbwOptions::new());
+    ybw.set_max_depth(6);
+    ybw.choose_move(b).unwrap();
+
+    vec![iterative.root_score(), mtdf.root_score(), lazy_smp.root_score(), ybw.root_score()]
+}
+
+#[test]
+fn mate_scores() {
+    // Three in a row with both ends open.
+    let b = board(&[1, 6, 2, 6, 3, 5]);
+    assert_eq!(vec![Score::WinIn(1); 4], scores(&b));
+    let b = board(&[1, 6, 2, 6, 3]);
+    assert_eq!(vec![Score::LossIn(2); 4], scores(&b));
+    // Either end makes three in a row with both ends open.
+    let b = board(&[1, 6, 2, 6]);
+    assert_eq!(vec![Score::WinIn(3); 4], scores(&b));
+
+    for score in scores(&connect4::Board::default()) {
+        assert!(matches!(score, Score::Eval(_)), "{:?}", score);
+    }
+
+    assert_eq!("win in 3 plies", Score::WinIn(3).to_string());
+    assert_eq!("loss in 2 plies", Score::from(WORST_EVAL + 2).to_string());
+    assert_eq!("-7", Score::from(-7).to_string());
+}
@@ -0,0 +1,101 @@
+// The best few moves of the root should each come with the score a search of
+// that move alone would give, best first.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+
+// The score of each move of the root, from a plain search of the position
+// after it.
+fn move_scores(b: &connect4::Board, depth: usize) -> Vec<(connect4::Place, Score)> {
+    let mut moves = Vec::new();
+    connect4::Game::generate_moves(b, &mut moves);
+    moves
+        .into_iter()
+        .map(|m| {
+            let mut b = b.clone();
+            m.apply(&mut b);
+            let mut negamax = Negamax::new(connect4::BasicEvaluator, depth - 1);
+            negamax.choose_move(&b).unwrap();
+            // Wins and losses are one ply further from this root.
+            let score = match Score::from(-negamax.root_value()) {
+                Score::WinIn(n) => Score::WinIn(n + 1),
+                Score::LossIn(n) => Score::LossIn(n + 1),
+                score => score,
+            };
+            (m, score)
+        })
+        .collect()
+}
+
+fn check_lines(
+    b: &connect4::Board, lines: &[(connect4::Place, Score, Vec<connect4::Place>)], k: usize,
+    depth: usize,
+) {
+    assert_eq!(k, lines.len());
+    let scores = move_scores(b, depth);
+    for (i, (m, score, pv)) in lines.iter().enumerate() {
+        assert_eq!(*m, pv[0]);
+        assert!(lines[..i].iter().all(|line| line.0 != *m));
+        // Ties may come in any order, but not a better move after a worse.
+        let best_other =
+            scores.iter().filter(|s| lines[..i].iter().all(|line| line.0 != s.0)).map(|s| s.1);
+        assert_eq!(Some(*score), best_other.max_by_key(|s| value(*s)));
+        assert_eq!(*score, scores.iter().find(|s| s.0 == *m).unwrap().1);
+    }
+}
+
+fn value(score: Score) -> Evaluation {
+    match score {
+        Score::WinIn(n) => BEST_EVAL - n as Evaluation,
+        Score::LossIn(n) => WORST_EVAL + n as Evaluation,
+        Score::Eval(value) => value,
+    }
+}
+
+#[test]
+fn multi_pv() {
+    let mut b = connect4::Board::default();
+    for m in [3, 3, 2] {
+        let mut moves = Vec::new();
+        connect4::Game::generate_moves(&b, &mut moves);
+        moves[m].apply(&mut b);
+    }
+
+    let mut iterative = IterativeSearch::new(
+        connect4::BasicEvaluator,
+        IterativeOptions::new().with_table_byte_size(64_000).with_multi_pv(3),
+    );
+    iterative.set_max_depth(5);
+    let m = iterative.choose_move(&b).unwrap();
+    let lines = iterative.principal_variations();
+    check_lines(&b, &lines, 3, 5);
+    assert_eq!(m, lines[0].0);
+    assert_eq!(iterative.root_score(), lines[0].1);
+    assert_eq!(iterative.principal_variation(), &lines[0].2[..]);
+
+    let mut lazy_smp = LazySmp::new(
+        connect4::BasicEvaluator,
+        LazySmpOptions::new().with_num_threads(3).with_table_byte_size(64_000).with_multi_pv(7),
+    );
+    lazy_smp.set_max_depth(5);
+    let m = lazy_smp.choose_move(&b).unwrap();
+    let lines = lazy_smp.principal_variations();
+    check_lines(&b, &lines, 7, 5);
+    assert_eq!(m, lines[0].0);
+
+    // Asking for more lines than moves gives all of them.
+    let mut iterative =
+        IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new().with_multi_pv(10));
+    iterative.set_max_depth(3);
+    iterative.choose_move(&b).unwrap();
+    check_lines(&b, &iterative.principal_variations(), 7, 3);
+
+    // A single line by default.
+    let mut iterative = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    iterative.set_max_depth(3);
+    iterative.choose_move(&b).unwrap();
+    assert_eq!(1, iterative.principal_variations().len());
+}
@@ -0,0 +1,83 @@
+// Proof-number search should find the same game-theoretic result as a full
+// width search to the end of the game. This file creates random connect four
+// positions close to the end of the game, and compares the solver against
+// IterativeSearch with an evaluator that scores every non-terminal state as
+// a draw.
+
+extern crate minimax;
+extern crate rand;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+use rand::seq::SliceRandom;
+
+fn generate_random_state(depth: usize) -> connect4::Board {
+    let mut rng = rand::thread_rng();
+    let mut b = connect4::Board::default();
+    for _ in 0..depth {
+        let mut moves = Vec::new();
+        connect4::Game::generate_moves(&b, &mut moves);
+        let m = moves.choose(&mut rng).unwrap();
+        m.apply(&mut b);
+        if connect4::Game::get_winner(&b).is_some() {
+            // Oops, undo and try again on the next iter.
+            m.undo(&mut b);
+        }
+    }
+    b
+}
+
+#[test]
+fn compare_full_search() {
+    for _ in 0..20 {
+        let mut b = generate_random_state(28);
+        let mut moves = Vec::new();
+        connect4::Game::generate_moves(&b, &mut moves);
+        if moves.is_empty() {
+            continue;
+        }
+
+        let mut iterative = IterativeSearch::new(
+            connect4::DumbEvaluator,
+            IterativeOptions::new().with_table_byte_size(1_000_000),
+        );
+        iterative.set_max_depth(42);
+        iterative.choose_move(&b).unwrap();
+        let value = iterative.root_value();
+        let expected = if value > 0 {
+            Outcome::Win
+        } else if value < 0 {
+            Outcome::Loss
+        } else {
+            Outcome::Draw
+        };
+
+        let mut pns = ProofNumberSearch::<connect4::Game>::new(
+            PnsOptions::new().with_table_byte_size(1_000_000),
+        );
+        assert_eq!(Proof::Proven(expected), pns.solve(&b), "\n{}", b);
+
+        // The proof should be a legal line of play that reaches the result.
+        let pv = pns.principal_variation().to_vec();
+        assert!(!pv.is_empty(), "\n{}", b);
+        for m in pv.iter() {
+            assert!(connect4::Game::get_winner(&b).is_none());
+            moves.clear();
+            connect4::Game::generate_moves(&b, &mut moves);
+            assert!(moves.contains(m), "\n{}", b);
+            m.apply(&mut b);
+        }
+        let winner = connect4::Game::get_winner(&b);
+        let last_mover_won = winner == Some(Winner::PlayerJustMoved);
+        let root_player_lost = last_mover_won && pv.len().is_multiple_of(2);
+        let root_player_won = last_mover_won && !root_player_lost;
+        match expected {
+            Outcome::Win => assert!(root_player_won, "\n{}", b),
+            Outcome::Loss => assert!(root_player_lost, "\n{}", b),
+            // The opponent's moves in a drawing line are arbitrary, so they
+            // may even lose.
+            Outcome::Draw => assert!(!root_player_lost, "\n{}", b),
+        }
+    }
+}
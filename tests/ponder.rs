@@ -0,0 +1,67 @@
+// Pondering should not change the result of a search, whether or not the
+// opponent plays the predicted reply.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+use std::time::{Duration, Instant};
+
+fn fresh_value(b: &connect4::Board, depth: usize) -> Evaluation {
+    let mut search = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    search.set_max_depth(depth);
+    search.choose_move(b).unwrap();
+    search.root_value()
+}
+
+#[test]
+fn ponder_hit_and_miss() {
+    let mut search = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    search.set_max_depth(6);
+    let mut ponderer = PonderingStrategy::new(search);
+
+    let mut b = connect4::Board::default();
+    for hit in [true, false, true, false] {
+        let m = ponderer.choose_move(&b).unwrap();
+        m.apply(&mut b);
+        let predicted = ponderer.inner_mut().principal_variation()[1];
+        assert!(ponderer.start_pondering(&b));
+        assert!(ponderer.is_pondering());
+
+        // The opponent plays the predicted reply, or some other move.
+        let mut moves = Vec::new();
+        connect4::Game::generate_moves(&b, &mut moves);
+        let reply = if hit { predicted } else { *moves.iter().find(|&&m| m != predicted).unwrap() };
+        reply.apply(&mut b);
+
+        let m = ponderer.choose_move(&b).unwrap();
+        assert!(!ponderer.is_pondering());
+        moves.clear();
+        connect4::Game::generate_moves(&b, &mut moves);
+        assert!(moves.contains(&m));
+        assert_eq!(fresh_value(&b, 6), ponderer.inner_mut().root_value(), "\n{}", b);
+    }
+}
+
+#[test]
+fn ponder_hit_timeout() {
+    let mut search = LazySmp::new(connect4::BasicEvaluator, LazySmpOptions::new());
+    search.set_timeout(Duration::from_millis(50));
+    let mut ponderer = PonderingStrategy::new(search);
+
+    let mut b = connect4::Board::default();
+    let m = ponderer.choose_move(&b).unwrap();
+    m.apply(&mut b);
+    let predicted = ponderer.inner_mut().principal_variation()[1];
+    assert!(ponderer.start_pondering(&b));
+    std::thread::sleep(Duration::from_millis(100));
+    predicted.apply(&mut b);
+
+    // The ponder search keeps going for the usual time after the hit.
+    let start = Instant::now();
+    assert!(ponderer.choose_move(&b).is_some());
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(40), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+}
@@ -0,0 +1,74 @@
+// Progress callbacks should report each completed iteration, and the final
+// report should match the result of the search.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct Report {
+    depth: u8,
+    complete: bool,
+    value: Evaluation,
+    nodes: u64,
+    pv: Vec<connect4::Place>,
+}
+
+type Reports = Arc<Mutex<Vec<Report>>>;
+
+fn recorder(reports: &Reports) -> impl FnMut(&SearchProgress<connect4::Place>) + Send {
+    let reports = reports.clone();
+    move |progress| {
+        reports.lock().unwrap().push(Report {
+            depth: progress.depth,
+            complete: progress.complete,
+            value: progress.value,
+            nodes: progress.nodes,
+            pv: progress.pv.to_vec(),
+        })
+    }
+}
+
+fn check_reports(reports: &Reports, value: Evaluation, pv: &[connect4::Place]) {
+    let reports = reports.lock().unwrap();
+    let complete: Vec<&Report> = reports.iter().filter(|r| r.complete).collect();
+    // Every depth up to 5 is reported once, in order.
+    assert_eq!((0..6).collect::<Vec<u8>>(), complete.iter().map(|r| r.depth).collect::<Vec<_>>());
+    let last = complete.last().unwrap();
+    assert_eq!(value, last.value);
+    assert_eq!(pv, &last.pv[..]);
+    // With no interval, there are also reports during iterations.
+    assert!(reports.iter().any(|r| !r.complete));
+    for pair in reports.windows(2) {
+        assert!(pair[0].nodes <= pair[1].nodes);
+    }
+}
+
+#[test]
+fn progress_reports() {
+    let b = connect4::Board::default();
+
+    let reports = Reports::default();
+    let mut iterative = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    iterative.set_max_depth(5);
+    iterative.set_progress_callback(Duration::new(0, 0), recorder(&reports));
+    iterative.choose_move(&b).unwrap();
+    check_reports(&reports, iterative.root_value(), iterative.principal_variation());
+
+    let reports = Reports::default();
+    let mut lazy_smp = LazySmp::new(connect4::BasicEvaluator, LazySmpOptions::new());
+    lazy_smp.set_max_depth(5);
+    lazy_smp.set_progress_callback(Duration::new(0, 0), recorder(&reports));
+    lazy_smp.choose_move(&b).unwrap();
+    check_reports(&reports, lazy_smp.root_value(), lazy_smp.principal_variation());
+
+    let reports = Reports::default();
+    let mut ybw = ParallelYbw::new(connect4::BasicEvaluator, YbwOptions::new());
+    ybw.set_max_depth(5);
+    ybw.set_progress_callback(Duration::new(0, 0), recorder(&reports));
+    ybw.choose_move(&b).unwrap();
+    check_reports(&reports, ybw.root_value(), ybw.principal_variation());
+}
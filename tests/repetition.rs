@@ -0,0 +1,142 @@
+// A game where the only thing to do is step around a cycle, so every line of
+// play eventually repeats a position. Strategies with a repetition rule
+// should score these as draws, and strategies without one should still
+// terminate.
+
+extern crate minimax;
+
+use minimax::util::battle_royale_with_repetition;
+use minimax::*;
+
+#[derive(Clone, Default)]
+struct Board {
+    pos: u8,
+    to_move: u8,
+}
+
+impl Board {
+    fn new(pos: u8, to_move: u8) -> Self {
+        Board { pos, to_move }
+    }
+}
+
+impl Zobrist for Board {
+    fn zobrist_hash(&self) -> u64 {
+        (self.pos as u64 * 2 + self.to_move as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Step(u8);
+
+impl Move for Step {
+    type G = Cycle;
+    fn apply(&self, b: &mut Board) {
+        b.pos = (b.pos + self.0) % 4;
+        b.to_move ^= 1;
+    }
+    fn undo(&self, b: &mut Board) {
+        b.pos = (b.pos + 4 - self.0) % 4;
+        b.to_move ^= 1;
+    }
+}
+
+struct Cycle;
+
+impl Game for Cycle {
+    type S = Board;
+    type M = Step;
+
+    fn generate_moves(_: &Board, moves: &mut Vec<Step>) {
+        moves.push(Step(1));
+        moves.push(Step(3));
+    }
+
+    fn get_winner(_: &Board) -> Option<Winner> {
+        None
+    }
+}
+
+// Whoever is to move likes their position.
+#[derive(Clone)]
+struct Content;
+
+impl Evaluator for Content {
+    type G = Cycle;
+    fn evaluate(&self, _: &Board) -> Evaluation {
+        10
+    }
+}
+
+#[test]
+fn repetitions_are_draws() {
+    let b = Board::default();
+    for rule in [RepetitionRule::Twofold, RepetitionRule::Threefold] {
+        let opts = IterativeOptions::new().with_repetition_rule(rule);
+
+        let mut iterative = IterativeSearch::new(Content, opts);
+        iterative.set_max_depth(12);
+        assert!(iterative.choose_move(&b).is_some());
+        assert_eq!(0, iterative.root_value(), "{:?}", rule);
+
+        let mut mtdf = MtdfSearch::new(Content, opts);
+        mtdf.set_max_depth(12);
+        assert!(mtdf.choose_move(&b).is_some());
+        assert_eq!(0, mtdf.root_value(), "{:?}", rule);
+
+        let mut lazysmp = LazySmp::new(Content, LazySmpOptions::new().with_repetition_rule(rule));
+        lazysmp.set_max_depth(12);
+        assert!(lazysmp.choose_move(&b).is_some());
+        assert_eq!(0, lazysmp.root_value(), "{:?}", rule);
+    }
+
+    // Without a rule, the leaves are all evaluated statically.
+    let mut iterative = IterativeSearch::new(Content, IterativeOptions::new());
+    iterative.set_max_depth(12);
+    assert!(iterative.choose_move(&b).is_some());
+    assert_eq!(-10, iterative.root_value());
+    // There are only four reachable positions, so the principal variation
+    // stops at the first repetition.
+    assert!(iterative.principal_variation().len() <= 4);
+}
+
+#[test]
+fn game_history_repetitions() {
+    let b = Board::default();
+    // Both moves from the root lead back to positions from the game.
+    let history = [Board::new(1, 1).zobrist_hash(), Board::new(3, 1).zobrist_hash()];
+
+    let mut iterative = IterativeSearch::new(
+        Content,
+        IterativeOptions::new().with_repetition_rule(RepetitionRule::Twofold),
+    );
+    iterative.set_max_depth(0);
+    iterative.choose_move(&b);
+    assert_eq!(-10, iterative.root_value());
+    iterative.set_game_history(&history);
+    iterative.choose_move(&b);
+    assert_eq!(0, iterative.root_value());
+
+    // The positions need to have occurred twice before for threefold
+    // repetition.
+    let mut iterative = IterativeSearch::new(
+        Content,
+        IterativeOptions::new().with_repetition_rule(RepetitionRule::Threefold),
+    );
+    iterative.set_max_depth(0);
+    iterative.set_game_history(&history);
+    iterative.choose_move(&b);
+    assert_eq!(-10, iterative.root_value());
+    iterative.set_game_history(&[history[0], history[1], history[0], history[1]]);
+    iterative.choose_move(&b);
+    assert_eq!(0, iterative.root_value());
+}
+
+#[test]
+fn adjudicate_repetition() {
+    let mut s1 = IterativeSearch::new(Content, IterativeOptions::new());
+    s1.set_max_depth(3);
+    let mut s2 = IterativeSearch::new(Content, IterativeOptions::new());
+    s2.set_max_depth(3);
+    assert_eq!(None, battle_royale_with_repetition(&mut s1, &mut s2, 3));
+}
@@ -0,0 +1,137 @@
+// Searches restricted to some moves of the root should find the best of
+// those moves, and leave nothing behind that misleads a later search of all
+// of them.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+
+const DEPTH: usize = 5;
+
+// The value of each move of the root, from a plain search of the position
+// after it.
+fn move_values(b: &connect4::Board) -> Vec<(connect4::Place, Evaluation)> {
+    let mut moves = Vec::new();
+    connect4::Game::generate_moves(b, &mut moves);
+    moves
+        .into_iter()
+        .map(|m| {
+            let mut b = b.clone();
+            m.apply(&mut b);
+            let mut negamax = Negamax::new(connect4::BasicEvaluator, DEPTH - 1);
+            negamax.choose_move(&b).unwrap();
+            // Wins and losses are one ply further from this root.
+            let value = match -negamax.root_value() {
+                value if value > BEST_EVAL - WIN_DISTANCE => value - 1,
+                value if value < WORST_EVAL + WIN_DISTANCE => value + 1,
+                value => value,
+            };
+            (m, value)
+        })
+        .collect()
+}
+
+// Search with each strategy, and return the moves and values they found.
+fn search(
+    b: &connect4::Board, root_moves: &RootMoves<connect4::Place>,
+) -> Vec<Option<(connect4::Place, Evaluation)>> {
+    let mut results = Vec::new();
+
+    let mut negamax = Negamax::new(connect4::BasicEvaluator, DEPTH);
+    negamax.set_root_moves(root_moves.clone());
+    results.push(negamax.choose_move(b).map(|m| (m, negamax.root_value())));
+
+    let mut iterative = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    iterative.set_max_depth(DEPTH);
+    iterative.set_root_moves(root_moves.clone());
+    results.push(iterative.choose_move(b).map(|m| (m, iterative.root_value())));
+
+    let mut lazy_smp =
+        LazySmp::new(connect4::BasicEvaluator, LazySmpOptions::new().with_num_threads(3));
+    lazy_smp.set_max_depth(DEPTH);
+    lazy_smp.set_root_moves(root_moves.clone());
+    results.push(lazy_smp.choose_move(b).map(|m| (m, lazy_smp.root_value())));
+
+    let mut ybw = ParallelYbw::new(connect4::BasicEvaluator, YbwOptions::new());
+    ybw.set_max_depth(DEPTH);
+    ybw.set_root_moves(root_moves.clone());
+    results.push(ybw.choose_move(b).map(|m| (m, ybw.root_value())));
+
+    results
+}
+
+#[test]
+fn root_moves() {
+    let mut b = connect4::Board::default();
+    for m in [3, 3, 2] {
+        let mut moves = Vec::new();
+        connect4::Game::generate_moves(&b, &mut moves);
+        moves[m].apply(&mut b);
+    }
+    let values = move_values(&b);
+    let best = values.iter().map(|v| v.1).max().unwrap();
+    let best_move = values.iter().find(|v| v.1 == best).unwrap().0;
+
+    for root_moves in [
+        RootMoves::All,
+        RootMoves::Only(vec![values[0].0, values[4].0, values[6].0]),
+        RootMoves::Only(vec![values[5].0]),
+        RootMoves::Except(vec![best_move]),
+    ] {
+        let allowed: Vec<_> = values.iter().filter(|v| root_moves.allows(&v.0)).collect();
+        let best = allowed.iter().map(|v| v.1).max().unwrap();
+        for result in search(&b, &root_moves) {
+            let (m, value) = result.unwrap();
+            assert_eq!(best, value, "{:?}", root_moves);
+            assert!(allowed.contains(&&(m, value)), "{:?}", root_moves);
+        }
+    }
+
+    // No moves to choose from.
+    for result in search(&b, &RootMoves::Only(Vec::new())) {
+        assert_eq!(None, result);
+    }
+}
+
+#[test]
+fn restricted_table() {
+    let b = connect4::Board::default();
+    let mut moves = Vec::new();
+    connect4::Game::generate_moves(&b, &mut moves);
+    let mut negamax = Negamax::new(connect4::BasicEvaluator, DEPTH);
+    negamax.choose_move(&b).unwrap();
+
+    // The restricted search doesn't store a worse value of the root for the
+    // next search to find.
+    let mut iterative = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    iterative.set_max_depth(DEPTH);
+    iterative.set_root_moves(RootMoves::Only(vec![moves[0]]));
+    assert_eq!(Some(moves[0]), iterative.choose_move(&b));
+    iterative.set_root_moves(RootMoves::All);
+    iterative.choose_move(&b).unwrap();
+    assert_eq!(negamax.root_value(), iterative.root_value());
+
+    let mut ybw = ParallelYbw::new(connect4::BasicEvaluator, YbwOptions::new());
+    ybw.set_max_depth(DEPTH);
+    ybw.set_root_moves(RootMoves::Only(vec![moves[0]]));
+    assert_eq!(Some(moves[0]), ybw.choose_move(&b));
+    ybw.set_root_moves(RootMoves::All);
+    ybw.choose_move(&b).unwrap();
+    assert_eq!(negamax.root_value(), ybw.root_value());
+
+    // Only the allowed moves are analyzed.
+    let mut iterative =
+        IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new().with_multi_pv(3));
+    iterative.set_max_depth(DEPTH);
+    iterative.set_root_moves(RootMoves::Only(vec![moves[0], moves[1]]));
+    iterative.choose_move(&b).unwrap();
+    let lines = iterative.principal_variations();
+    assert_eq!(2, lines.len());
+    assert!(lines.iter().all(|line| line.0 == moves[0] || line.0 == moves[1]));
+    iterative.set_root_moves(RootMoves::All);
+    iterative.choose_move(&b).unwrap();
+    assert_eq!(3, iterative.principal_variations().len());
+    assert_eq!(negamax.root_value(), iterative.root_value());
+}
@@ -0,0 +1,85 @@
+// Strategies with the same seed should play the same moves, and the parallel
+// searches should be repeatable in their serial deterministic mode.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::strategies::random::Random;
+use minimax::*;
+
+// Play a few moves from the start, and record the moves with any other
+// results worth comparing.
+fn play<S, F, T>(strategy: &mut S, mut results: F) -> Vec<(connect4::Place, T)>
+where
+    S: Strategy<connect4::Game>,
+    F: FnMut(&S) -> T,
+{
+    let mut b = connect4::Board::default();
+    let mut moves = Vec::new();
+    for _ in 0..6 {
+        let m = strategy.choose_move(&b).unwrap();
+        m.apply(&mut b);
+        moves.push((m, results(strategy)));
+    }
+    moves
+}
+
+#[test]
+fn seeded_strategies() {
+    let random = |seed| {
+        let mut random = Random::new();
+        random.set_seed(seed);
+        play(&mut random, |_| ())
+    };
+    assert_eq!(random(3), random(3));
+    assert_ne!(random(3), random(4));
+
+    // All moves are tied, so this is random too.
+    let negamax = |seed| {
+        let mut negamax = Negamax::new(connect4::DumbEvaluator, 2);
+        negamax.set_seed(seed);
+        play(&mut negamax, |_| ())
+    };
+    assert_eq!(negamax(3), negamax(3));
+    assert_ne!(negamax(3), negamax(4));
+
+    let mcts = |seed| {
+        let mut mcts = MonteCarloTreeSearch::<connect4::Game>::new(
+            MctsOptions::new().with_seed(seed),
+        );
+        mcts.set_max_rollouts(200);
+        play(&mut mcts, |_| ())
+    };
+    assert_eq!(mcts(3), mcts(3));
+    assert_ne!(mcts(3), mcts(4));
+}
+
+#[test]
+fn deterministic_search() {
+    let limits = SearchLimits::new().with_depth(7).with_nodes(5000);
+
+    let lazy_smp = || {
+        let mut lazy_smp = LazySmp::new(
+            connect4::BasicEvaluator,
+            LazySmpOptions::new()
+                .with_num_threads(4)
+                .with_table_byte_size(16_000)
+                .with_seed(7)
+                .with_deterministic_search(),
+        );
+        lazy_smp.set_limits(limits.clone());
+        play(&mut lazy_smp, |s| (s.principal_variation().to_vec(), s.stats().thread_nodes))
+    };
+    assert_eq!(lazy_smp(), lazy_smp());
+
+    let ybw = || {
+        let mut ybw = ParallelYbw::new(
+            connect4::BasicEvaluator,
+            YbwOptions::new().with_table_byte_size(16_000).with_deterministic_search(),
+        );
+        ybw.set_limits(limits.clone());
+        play(&mut ybw, |s| (s.principal_variation().to_vec(), s.stats().total_nodes()))
+    };
+    assert_eq!(ybw(), ybw());
+}
@@ -0,0 +1,65 @@
+// Many threads sharing a tiny transposition table write the same entries at
+// the same time. Their searches should still agree with a serial search.
+
+extern crate minimax;
+extern crate rand;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+use rand::seq::SliceRandom;
+
+#[derive(Clone)]
+struct HashEvaluator;
+
+impl Evaluator for HashEvaluator {
+    type G = connect4::Game;
+    fn evaluate(&self, b: &connect4::Board) -> Evaluation {
+        (b.zobrist_hash() as Evaluation) >> 25
+    }
+}
+
+fn random_board(moves: usize) -> connect4::Board {
+    let mut rng = rand::thread_rng();
+    let mut b = connect4::Board::default();
+    for _ in 0..moves {
+        let mut moves = Vec::new();
+        connect4::Game::generate_moves(&b, &mut moves);
+        match moves.choose(&mut rng) {
+            Some(m) => m.apply(&mut b),
+            None => break,
+        }
+    }
+    b
+}
+
+#[test]
+fn shared_table_stress() {
+    for _ in 0..20 {
+        let b = random_board(8);
+        if connect4::Game::get_winner(&b).is_some() {
+            continue;
+        }
+        let mut negamax = Negamax::new(HashEvaluator, 6);
+        negamax.choose_move(&b).unwrap();
+        let value = negamax.root_value();
+
+        let mut lazy_smp = LazySmp::new_packed(
+            HashEvaluator,
+            LazySmpOptions::new().with_num_threads(16).with_table_byte_size(2048),
+        );
+        lazy_smp.set_max_depth(6);
+        lazy_smp.choose_move(&b).unwrap();
+        assert_eq!(value, lazy_smp.root_value(), "{}", b);
+
+        let mut ybw = ParallelYbw::new_packed(
+            HashEvaluator,
+            YbwOptions::new()
+                .with_table_byte_size(2048)
+                .with_replacement_strategy(Replacement::Bucket(4)),
+        );
+        ybw.set_max_depth(6);
+        ybw.choose_move(&b).unwrap();
+        assert_eq!(value, ybw.root_value(), "{}", b);
+    }
+}
@@ -0,0 +1,51 @@
+// Higher skill levels should do better against a full-strength search, and
+// the top level should play as well as it.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::util::battle_royale;
+use minimax::*;
+
+// Points against a full-strength search to the same depth, with 2 for a win
+// and 1 for a draw, alternating who plays first.
+fn match_points(level: u8, games: u64) -> u32 {
+    let mut points = 0;
+    for game in 0..games {
+        let mut skill = SkillLevel::new(
+            connect4::BasicEvaluator,
+            SkillOptions::new()
+                .with_level(level)
+                .with_max_depth(4)
+                .with_eval_scale(10)
+                .with_seed(game),
+        );
+        let mut full = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+        full.set_max_depth(4);
+        let winner = if game % 2 == 0 {
+            battle_royale(&mut skill, &mut full)
+        } else {
+            battle_royale(&mut full, &mut skill).map(|winner| 1 - winner)
+        };
+        points += match winner {
+            Some(0) => 2,
+            None => 1,
+            _ => 0,
+        };
+    }
+    points
+}
+
+#[test]
+fn skill_levels() {
+    let games = 40;
+    let weakest = match_points(1, games);
+    let middle = match_points(10, games);
+    let strongest = match_points(MAX_SKILL_LEVEL, games);
+    assert!(weakest < middle && middle < strongest, "{} {} {}", weakest, middle, strongest);
+    // A quarter of the points at most.
+    assert!(weakest as u64 <= games / 2, "{}", weakest);
+    // Only the order of tied moves differs, so this is close to half.
+    assert!(strongest as u64 >= games * 2 / 3, "{}", strongest);
+}
@@ -0,0 +1,74 @@
+// Search statistics should add up, across depths and across threads.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+
+fn check_stats(stats: &SearchStats, threads: usize) {
+    assert_eq!(5, stats.depth);
+    assert_eq!(6, stats.nodes_per_depth.len());
+    assert_eq!(threads, stats.thread_nodes.len());
+    assert!(stats.generate_move_calls > 0);
+    let mbf = stats.mean_branching_factor();
+    assert!(mbf > 1.0 && mbf <= 7.0, "{}", mbf);
+    assert!(stats.first_move_cutoffs <= stats.beta_cutoffs);
+    assert!(stats.nodes_per_sec() > 0.0);
+    let summary = stats.to_string();
+    assert!(summary.starts_with("Explored "), "{}", summary);
+    assert!(summary.ends_with(" nodes/sec"), "{}", summary);
+}
+
+#[test]
+fn iterative_stats() {
+    let b = connect4::Board::default();
+    let mut iterative = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    iterative.set_max_depth(5);
+    iterative.choose_move(&b).unwrap();
+    let stats = iterative.stats();
+    check_stats(&stats, 0);
+    assert!(stats.nodes_per_depth.windows(2).all(|w| w[0] < w[1]), "{:?}", stats);
+    assert_eq!(stats.nodes_per_depth.iter().sum::<u64>(), stats.total_nodes());
+    assert!(stats.to_string().contains(" to depth 5."));
+
+    let mut mtdf = MtdfSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    mtdf.set_max_depth(5);
+    mtdf.choose_move(&b).unwrap();
+    let stats = mtdf.stats();
+    check_stats(&stats, 0);
+    assert!(stats.zero_window_searches.unwrap() >= 6);
+    assert!(stats.to_string().contains("zero-window searches"));
+}
+
+#[test]
+fn parallel_stats() {
+    let b = connect4::Board::default();
+    let mut lazy_smp =
+        LazySmp::new(connect4::BasicEvaluator, LazySmpOptions::new().with_num_threads(3));
+    lazy_smp.set_max_depth(5);
+    lazy_smp.choose_move(&b).unwrap();
+    let stats = lazy_smp.stats();
+    check_stats(&stats, 3);
+    // The main thread is counted by depth.
+    assert_eq!(stats.nodes_per_depth.iter().sum::<u64>(), stats.thread_nodes[0]);
+    assert!(stats.to_string().contains("Nodes per thread: "));
+
+    let mut ybw = ParallelYbw::new(connect4::BasicEvaluator, YbwOptions::new());
+    ybw.set_max_depth(5);
+    ybw.choose_move(&b).unwrap();
+    let stats = ybw.stats();
+    check_stats(&stats, rayon::current_num_threads() + 1);
+    // Every thread is counted by depth.
+    assert_eq!(stats.nodes_per_depth.iter().sum::<u64>(), stats.total_nodes());
+}
+
+#[test]
+fn empty_stats() {
+    let stats = SearchStats::default();
+    assert_eq!(0.0, stats.mean_branching_factor());
+    assert_eq!(0.0, stats.first_move_cutoff_rate());
+    assert_eq!(0.0, stats.nodes_per_sec());
+    let summary = stats.to_string();
+    assert!(!summary.contains("NaN") && !summary.contains("inf"), "{}", summary);
+}
@@ -3,6 +3,9 @@
 // for speed, but it's all fundamentally the minimax algorithm. This file
 // creates fake evaluation trees of connect four, and ensures that all
 // Strategies (including a plain negamax without alpha-beta) get the same answer.
+// Quiescence search is checked the same way, with some connect four moves
+// arbitrarily considered noisy, and null-move pruning with a game of taking
+// coins where passing is only allowed when it's safe.
 
 extern crate minimax;
 extern crate rand;
@@ -11,6 +14,7 @@ mod connect4;
 
 use minimax::*;
 use rand::seq::SliceRandom;
+use rand::Rng;
 use std::cmp::max;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
@@ -25,22 +29,24 @@ pub struct PlainNegamax<E: Evaluator> {
 
 impl<E: Evaluator> PlainNegamax<E> {
     pub fn new(eval: E, depth: usize) -> PlainNegamax<E> {
-        PlainNegamax { depth: depth, root_value: 0, best_moves: Vec::new(), eval }
+        PlainNegamax { depth, root_value: 0, best_moves: Vec::new(), eval }
     }
 
     fn negamax(&self, s: &mut <E::G as Game>::S, depth: usize) -> Evaluation
     where
         <<E as Evaluator>::G as Game>::M: Copy,
     {
+        // The children of the root are searched at self.depth.
+        let ply = self.depth - depth + 1;
         if let Some(winner) = E::G::get_winner(s) {
-            return winner.evaluate();
+            return winner.evaluate_at_ply(ply);
         }
         if depth == 0 {
-            return self.eval.evaluate(s);
+            return self.quiescence(s, ply);
         }
         let mut moves = Vec::new();
         E::G::generate_moves(s, &mut moves);
-        let mut best = WORST_EVAL;
+        let mut best = WORST_EVAL + ply as Evaluation;
         for m in moves.iter() {
             m.apply(s);
             let value = -self.negamax(s, depth - 1);
@@ -49,6 +55,27 @@ impl<E: Evaluator> PlainNegamax<E> {
         }
         best
     }
+
+    // Unlimited search of noisy moves, where the player to move can also
+    // stand pat. Just the evaluation for games without noisy moves.
+    fn quiescence(&self, s: &mut <E::G as Game>::S, ply: usize) -> Evaluation
+    where
+        <<E as Evaluator>::G as Game>::M: Copy,
+    {
+        if let Some(winner) = E::G::get_winner(s) {
+            return winner.evaluate_at_ply(ply);
+        }
+        let mut moves = Vec::new();
+        E::G::generate_noisy_moves(s, &mut moves);
+        let mut best = self.eval.evaluate(s);
+        for m in moves.iter() {
+            m.apply(s);
+            let value = -self.quiescence(s, ply + 1);
+            m.undo(s);
+            best = max(best, value);
+        }
+        best
+    }
 }
 
 impl<E: Evaluator> Strategy<E::G> for PlainNegamax<E>
@@ -76,29 +103,91 @@ where
             }
         }
         self.root_value = best_value;
-        self.best_moves.first().map(|m| *m)
+        self.best_moves.first().copied()
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 struct RandomEvaluator;
 
-impl Default for RandomEvaluator {
-    fn default() -> Self {
-        Self {}
-    }
-}
-
 impl minimax::Evaluator for RandomEvaluator {
     type G = connect4::Game;
     fn evaluate(&self, b: &connect4::Board) -> minimax::Evaluation {
-        // Hash the game state to get a deterministically random Evaluation.
-        let mut hasher = DefaultHasher::new();
-        hasher.write_u64(b.pieces_just_moved());
-        hasher.write_u64(b.pieces_to_move);
-        let hash = hasher.finish();
         // Use fewer bits so that we get some equal values.
-        (hash as minimax::Evaluation) >> 25
+        (random_hash(b) as minimax::Evaluation) >> 25
+    }
+}
+
+// Hash the game state to get deterministically random values.
+fn random_hash(b: &connect4::Board) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u64(b.pieces_just_moved());
+    hasher.write_u64(b.pieces_to_move);
+    hasher.finish()
+}
+
+// Connect four, where about an eighth of the moves are noisy.
+struct NoisyConnect4;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct NoisyPlace(connect4::Place);
+
+impl minimax::Move for NoisyPlace {
+    type G = NoisyConnect4;
+    fn apply(&self, b: &mut connect4::Board) {
+        self.0.apply(b)
+    }
+    fn undo(&self, b: &mut connect4::Board) {
+        self.0.undo(b)
+    }
+}
+
+impl minimax::Game for NoisyConnect4 {
+    type S = connect4::Board;
+    type M = NoisyPlace;
+
+    fn generate_moves(b: &connect4::Board, moves: &mut Vec<NoisyPlace>) {
+        let mut places = Vec::new();
+        connect4::Game::generate_moves(b, &mut places);
+        moves.extend(places.into_iter().map(NoisyPlace));
+    }
+
+    fn generate_noisy_moves(b: &connect4::Board, moves: &mut Vec<NoisyPlace>) {
+        let mut places = Vec::new();
+        Self::generate_moves(b, &mut places);
+        let mut b = b.clone();
+        moves.extend(places.into_iter().filter(|m| {
+            m.apply(&mut b);
+            let noisy = random_hash(&b).is_multiple_of(8);
+            m.undo(&mut b);
+            noisy
+        }));
+    }
+
+    fn delta_pruning_margin(b: &connect4::Board, m: &NoisyPlace) -> Option<minimax::Evaluation> {
+        // The evaluation is always in [-64, 64), so a move that doesn't end
+        // the game can't gain more than this.
+        let mut b = b.clone();
+        m.apply(&mut b);
+        if Self::get_winner(&b).is_some() {
+            None
+        } else {
+            Some(128)
+        }
+    }
+
+    fn get_winner(b: &connect4::Board) -> Option<Winner> {
+        connect4::Game::get_winner(b)
+    }
+}
+
+#[derive(Clone, Default)]
+struct NoisyRandomEvaluator;
+
+impl minimax::Evaluator for NoisyRandomEvaluator {
+    type G = NoisyConnect4;
+    fn evaluate(&self, b: &connect4::Board) -> minimax::Evaluation {
+        RandomEvaluator.evaluate(b)
     }
 }
 
@@ -118,13 +207,130 @@ fn generate_random_state(depth: usize) -> connect4::Board {
     b
 }
 
+// Players take turns taking coins from a row, and the evaluation is how
+// much more the player to move has taken. While every coin left is worth
+// something, having the move never hurts and passing is safe, but with a
+// negative coin left a player might be forced to take it (zugzwang), so
+// `null_move` refuses to pass.
+struct Coins;
+
+#[derive(Clone)]
+struct CoinsState {
+    coins: Vec<i8>,
+    // Coins taken by each player, as bitmasks.
+    taken: [u32; 2],
+    to_move: usize,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum CoinMove {
+    Take(u8),
+    Pass,
+}
+
+impl CoinsState {
+    fn remaining(&self) -> impl Iterator<Item = (usize, i8)> + '_ {
+        let taken = self.taken[0] | self.taken[1];
+        self.coins.iter().copied().enumerate().filter(move |&(i, _)| taken & (1 << i) == 0)
+    }
+
+    fn score(&self, player: usize) -> Evaluation {
+        self.coins
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| self.taken[player] & (1 << i) != 0)
+            .map(|(_, &coin)| coin as Evaluation)
+            .sum()
+    }
+
+    fn lead(&self) -> Evaluation {
+        self.score(self.to_move) - self.score(1 - self.to_move)
+    }
+}
+
+impl minimax::Zobrist for CoinsState {
+    fn zobrist_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u32(self.taken[0]);
+        hasher.write_u32(self.taken[1]);
+        hasher.write_usize(self.to_move);
+        hasher.finish()
+    }
+}
+
+impl minimax::Move for CoinMove {
+    type G = Coins;
+    fn apply(&self, s: &mut CoinsState) {
+        if let CoinMove::Take(i) = *self {
+            s.taken[s.to_move] |= 1 << i;
+        }
+        s.to_move = 1 - s.to_move;
+    }
+    fn undo(&self, s: &mut CoinsState) {
+        s.to_move = 1 - s.to_move;
+        if let CoinMove::Take(i) = *self {
+            s.taken[s.to_move] &= !(1 << i);
+        }
+    }
+}
+
+impl minimax::Game for Coins {
+    type S = CoinsState;
+    type M = CoinMove;
+
+    fn generate_moves(s: &CoinsState, moves: &mut Vec<CoinMove>) {
+        moves.extend(s.remaining().map(|(i, _)| CoinMove::Take(i as u8)));
+    }
+
+    fn get_winner(s: &CoinsState) -> Option<Winner> {
+        if s.remaining().next().is_some() {
+            return None;
+        }
+        Some(match s.lead() {
+            0 => Winner::Draw,
+            lead if lead > 0 => Winner::PlayerToMove,
+            _ => Winner::PlayerJustMoved,
+        })
+    }
+
+    fn null_move(s: &CoinsState) -> Option<CoinMove> {
+        if s.remaining().all(|(_, coin)| coin >= 0) {
+            Some(CoinMove::Pass)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CoinsEvaluator;
+
+impl minimax::Evaluator for CoinsEvaluator {
+    type G = Coins;
+    fn evaluate(&self, s: &CoinsState) -> minimax::Evaluation {
+        s.lead()
+    }
+}
+
+// Enough coins that searches never reach the end of the game. Half of the
+// rows are mostly negative coins, where passing is never allowed and would
+// be better than any move.
+fn generate_random_coins() -> CoinsState {
+    let mut rng = rand::thread_rng();
+    let min = *[-9, 0].choose(&mut rng).unwrap();
+    let coins = (0..12).map(|_| rng.gen_range(min..min + 10)).collect();
+    CoinsState { coins, taken: [0; 2], to_move: 0 }
+}
+
 #[test]
 fn compare_plain_negamax() {
+    // Nodes searched in the game of coins for each null-move reduction.
+    let mut coins_nodes = [0; 3];
     for _ in 0..100 {
         for max_depth in 0..5 {
             let b = generate_random_state(10);
 
-            let mut plain_negamax = PlainNegamax::new(RandomEvaluator::default(), max_depth);
+            let mut plain_negamax = PlainNegamax::new(RandomEvaluator, max_depth);
             plain_negamax.choose_move(&b);
             let value = plain_negamax.root_value;
 
@@ -151,14 +357,24 @@ fn compare_plain_negamax() {
                 IterativeOptions::new()
                     .with_replacement_strategy(Replacement::TwoTier)
                     .with_aspiration_window(5),
+                IterativeOptions::new()
+                    .with_replacement_strategy(Replacement::Bucket(3))
+                    .with_null_window_search(false),
+                IterativeOptions::new().with_killer_moves(false).with_countermove_heuristic(false),
+                IterativeOptions::new()
+                    .with_killer_moves(false)
+                    .with_history_heuristic(false)
+                    .with_countermove_heuristic(false),
+                // Late move reductions only keep exact values without any reduction.
+                IterativeOptions::new().with_late_move_reductions(
+                    LateMoveReductions::new().with_min_depth(1).with_reduction_table(|_, _| 0),
+                ),
             ]
             .drain(..)
             .enumerate()
             {
-                let mut iterative = IterativeSearch::new(
-                    RandomEvaluator::default(),
-                    opt.with_table_byte_size(64000),
-                );
+                let mut iterative =
+                    IterativeSearch::new(RandomEvaluator, opt.with_table_byte_size(64000));
                 iterative.set_max_depth(max_depth);
                 let iterative_move = iterative.choose_move(&b).unwrap();
                 let iterative_value = iterative.root_value();
@@ -176,8 +392,24 @@ fn compare_plain_negamax() {
                 );
             }
 
+            let mut mtdf = MtdfSearch::new(
+                RandomEvaluator,
+                IterativeOptions::new().with_table_byte_size(64000),
+            );
+            mtdf.set_max_depth(max_depth);
+            let mtdf_move = mtdf.choose_move(&b).unwrap();
+            let mtdf_value = mtdf.root_value();
+            assert_eq!(value, mtdf_value, "search depth={}\n{}", max_depth, b);
+            assert!(
+                plain_negamax.best_moves.contains(&mtdf_move),
+                "bad move={:?}\nsearch depth={}\n{}",
+                mtdf_move,
+                max_depth,
+                b
+            );
+
             let mut ybw = ParallelYbw::new(
-                RandomEvaluator::default(),
+                RandomEvaluator,
                 YbwOptions::default().with_table_byte_size(64000),
             );
             ybw.set_max_depth(max_depth);
@@ -193,7 +425,7 @@ fn compare_plain_negamax() {
             );
 
             let mut lazysmp = LazySmp::new(
-                RandomEvaluator::default(),
+                RandomEvaluator,
                 LazySmpOptions::default().with_table_byte_size(64000),
             );
             lazysmp.set_max_depth(max_depth);
@@ -207,8 +439,60 @@ fn compare_plain_negamax() {
                 max_depth,
                 b
             );
+
+            let coins = generate_random_coins();
+            let mut plain_negamax = PlainNegamax::new(CoinsEvaluator, max_depth);
+            plain_negamax.choose_move(&coins);
+            let value = plain_negamax.root_value;
+            for (option_num, null_move_reduction) in [None, Some(1), Some(2)].iter().enumerate() {
+                let mut iterative_opts = IterativeOptions::new().with_table_byte_size(64000);
+                let mut lazysmp_opts = LazySmpOptions::new().with_table_byte_size(64000);
+                let mut ybw_opts = YbwOptions::new().with_table_byte_size(64000);
+                if let Some(reduction) = *null_move_reduction {
+                    iterative_opts = iterative_opts.with_null_move_pruning(reduction);
+                    lazysmp_opts = lazysmp_opts.with_null_move_pruning(reduction);
+                    ybw_opts = ybw_opts.with_null_move_pruning(reduction);
+                }
+
+                let mut iterative = IterativeSearch::new(CoinsEvaluator, iterative_opts);
+                iterative.set_max_depth(max_depth);
+                let iterative_move = iterative.choose_move(&coins).unwrap();
+                coins_nodes[option_num] += iterative.stats().total_nodes();
+                let mut lazysmp = LazySmp::new(CoinsEvaluator, lazysmp_opts);
+                lazysmp.set_max_depth(max_depth);
+                let lazysmp_move = lazysmp.choose_move(&coins).unwrap();
+                let mut ybw = ParallelYbw::new(CoinsEvaluator, ybw_opts);
+                ybw.set_max_depth(max_depth);
+                let ybw_move = ybw.choose_move(&coins).unwrap();
+
+                for (name, m, search_value) in [
+                    ("iterative", iterative_move, iterative.root_value()),
+                    ("lazysmp", lazysmp_move, lazysmp.root_value()),
+                    ("ybw", ybw_move, ybw.root_value()),
+                ]
+                .iter()
+                {
+                    assert_eq!(
+                        value, *search_value,
+                        "{} search depth={}, option={}\n{:?}",
+                        name, max_depth, option_num, coins.coins
+                    );
+                    assert!(
+                        plain_negamax.best_moves.contains(m),
+                        "{} bad move={:?}\nsearch depth={}, option={}\n{:?}",
+                        name,
+                        m,
+                        max_depth,
+                        option_num,
+                        coins.coins
+                    );
+                }
+            }
         }
     }
+    // Null-move pruning should have cut something.
+    assert!(coins_nodes[1] < coins_nodes[0], "{:?}", coins_nodes);
+    assert!(coins_nodes[2] < coins_nodes[0], "{:?}", coins_nodes);
 }
 
 // The same test, but a deeper tree (without plain negamax) to try to expose
@@ -224,7 +508,7 @@ fn compare_deep_negamax() {
             let value = negamax.root_value();
 
             let mut iterative = IterativeSearch::new(
-                RandomEvaluator::default(),
+                RandomEvaluator,
                 IterativeOptions::new().with_table_byte_size(64000),
             );
             iterative.set_max_depth(max_depth);
@@ -232,8 +516,17 @@ fn compare_deep_negamax() {
             let iterative_value = iterative.root_value();
             assert_eq!(value, iterative_value, "search depth={}\n{}", max_depth, b);
 
+            let mut mtdf = MtdfSearch::new(
+                RandomEvaluator,
+                IterativeOptions::new().with_table_byte_size(64000),
+            );
+            mtdf.set_max_depth(max_depth);
+            mtdf.choose_move(&b).unwrap();
+            let mtdf_value = mtdf.root_value();
+            assert_eq!(value, mtdf_value, "search depth={}\n{}", max_depth, b);
+
             let mut parallel = ParallelYbw::new(
-                RandomEvaluator::default(),
+                RandomEvaluator,
                 YbwOptions::default().with_table_byte_size(64000),
             );
             parallel.set_max_depth(max_depth);
@@ -242,8 +535,10 @@ fn compare_deep_negamax() {
             assert_eq!(value, parallel_value, "search depth={}\n{}", max_depth, b);
 
             let mut lazysmp = LazySmp::new(
-                RandomEvaluator::default(),
-                LazySmpOptions::default().with_table_byte_size(64000),
+                RandomEvaluator,
+                LazySmpOptions::default()
+                    .with_table_byte_size(64000)
+                    .with_replacement_strategy(Replacement::Bucket(4)),
             );
             lazysmp.set_max_depth(max_depth);
             lazysmp.choose_move(&b).unwrap();
@@ -252,3 +547,188 @@ fn compare_deep_negamax() {
         }
     }
 }
+
+// Quiescence search that is deep enough to always settle should match the
+// plain negamax, with or without a sound delta pruning margin.
+#[test]
+fn compare_plain_negamax_quiescence() {
+    for _ in 0..100 {
+        for max_depth in 0..4 {
+            let b = generate_random_state(10);
+
+            let mut plain_negamax = PlainNegamax::new(NoisyRandomEvaluator, max_depth);
+            plain_negamax.choose_move(&b);
+            let value = plain_negamax.root_value;
+
+            for (option_num, opt) in vec![
+                IterativeOptions::new(),
+                IterativeOptions::new().with_delta_pruning(),
+                IterativeOptions::new()
+                    .with_replacement_strategy(Replacement::Always)
+                    .with_null_window_search(false),
+            ]
+            .drain(..)
+            .enumerate()
+            {
+                let mut iterative = IterativeSearch::new(
+                    NoisyRandomEvaluator,
+                    opt.with_table_byte_size(64000).with_quiescence_search_depth(u8::MAX),
+                );
+                iterative.set_max_depth(max_depth);
+                let iterative_move = iterative.choose_move(&b).unwrap();
+                let iterative_value = iterative.root_value();
+                assert_eq!(
+                    value, iterative_value,
+                    "search depth={}, option={}\n{}",
+                    max_depth, option_num, b
+                );
+                assert!(
+                    plain_negamax.best_moves.contains(&iterative_move),
+                    "bad move={:?}\nsearch depth={}\n{}",
+                    iterative_move,
+                    max_depth,
+                    b
+                );
+            }
+
+            let mut mtdf = MtdfSearch::new(
+                NoisyRandomEvaluator,
+                IterativeOptions::new()
+                    .with_table_byte_size(64000)
+                    .with_quiescence_search_depth(u8::MAX),
+            );
+            mtdf.set_max_depth(max_depth);
+            mtdf.choose_move(&b).unwrap();
+            assert_eq!(value, mtdf.root_value(), "search depth={}\n{}", max_depth, b);
+
+            let mut ybw = ParallelYbw::new(
+                NoisyRandomEvaluator,
+                YbwOptions::default()
+                    .with_table_byte_size(64000)
+                    .with_quiescence_search_depth(u8::MAX)
+                    .with_delta_pruning(),
+            );
+            ybw.set_max_depth(max_depth);
+            ybw.choose_move(&b).unwrap();
+            assert_eq!(value, ybw.root_value(), "search depth={}\n{}", max_depth, b);
+
+            let mut lazysmp = LazySmp::new(
+                NoisyRandomEvaluator,
+                LazySmpOptions::default()
+                    .with_table_byte_size(64000)
+                    .with_quiescence_search_depth(u8::MAX),
+            );
+            lazysmp.set_max_depth(max_depth);
+            lazysmp.choose_move(&b).unwrap();
+            assert_eq!(value, lazysmp.root_value(), "search depth={}\n{}", max_depth, b);
+        }
+    }
+}
+
+// A game where every line after a move of the root is worth the same to the
+// root player, except that searches stopping one ply short misjudge the
+// last two moves: they overrate move 2, and underrate move 3, which is the
+// best and noisy. Late move reductions of one ply at the root must re-search
+// move 2 to see that it isn't best, and must not reduce move 3.
+struct LateMoves;
+
+#[derive(Clone, Default)]
+struct LateMovesState(Vec<u8>);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct LateMove(u8);
+
+// The depth to search, and the length of the lines it evaluates.
+const LATE_MOVES_DEPTH: usize = 4;
+const LATE_MOVES_FULL: usize = LATE_MOVES_DEPTH + 1;
+
+impl minimax::Zobrist for LateMovesState {
+    fn zobrist_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&self.0);
+        hasher.finish()
+    }
+}
+
+impl minimax::Move for LateMove {
+    type G = LateMoves;
+    fn apply(&self, s: &mut LateMovesState) {
+        s.0.push(self.0);
+    }
+    fn undo(&self, s: &mut LateMovesState) {
+        s.0.pop();
+    }
+}
+
+impl minimax::Game for LateMoves {
+    type S = LateMovesState;
+    type M = LateMove;
+
+    fn generate_moves(_: &LateMovesState, moves: &mut Vec<LateMove>) {
+        moves.extend((0..4).map(LateMove));
+    }
+
+    fn generate_noisy_moves(s: &LateMovesState, moves: &mut Vec<LateMove>) {
+        if s.0.is_empty() {
+            moves.push(LateMove(3));
+        }
+    }
+
+    fn get_winner(_: &LateMovesState) -> Option<Winner> {
+        None
+    }
+}
+
+struct LateMovesEvaluator;
+
+impl minimax::Evaluator for LateMovesEvaluator {
+    type G = LateMoves;
+    fn evaluate(&self, s: &LateMovesState) -> minimax::Evaluation {
+        let len = s.0.len();
+        let value = match (s.0[0], len) {
+            (0, _) | (1, _) => 10,
+            (2, LATE_MOVES_FULL) => 50,
+            (2, l) if l == LATE_MOVES_FULL - 1 => 100,
+            (3, LATE_MOVES_FULL) => 70,
+            _ => 0,
+        };
+        // From the perspective of the player to move.
+        if len.is_multiple_of(2) {
+            value
+        } else {
+            -value
+        }
+    }
+}
+
+#[test]
+fn late_move_reductions() {
+    let s = LateMovesState::default();
+    let mut plain_negamax = PlainNegamax::new(LateMovesEvaluator, LATE_MOVES_DEPTH);
+    assert_eq!(Some(LateMove(3)), plain_negamax.choose_move(&s));
+    assert_eq!(70, plain_negamax.root_value);
+
+    // Only the root of the last iteration is reduced, so earlier iterations
+    // don't see the misjudged lines and order the last two moves last.
+    let lmr = LateMoveReductions::new()
+        .with_min_depth(LATE_MOVES_DEPTH as u8 + 1)
+        .with_full_depth_moves(1)
+        .with_reduction_table(|_, _| 1);
+    let opts = IterativeOptions::new()
+        .with_double_step_increment()
+        .with_killer_moves(false)
+        .with_late_move_reductions(lmr);
+    let mut iterative = IterativeSearch::new(LateMovesEvaluator, opts);
+    iterative.set_max_depth(LATE_MOVES_DEPTH);
+    assert_eq!(Some(LateMove(3)), iterative.choose_move(&s));
+    assert_eq!(70, iterative.root_value());
+
+    // Reducing the noisy move too misses it.
+    let mut reduce_noisy = IterativeSearch::new(
+        LateMovesEvaluator,
+        opts.with_late_move_reductions(lmr.with_noisy_move_reductions(true)),
+    );
+    reduce_noisy.set_max_depth(LATE_MOVES_DEPTH);
+    assert_eq!(Some(LateMove(2)), reduce_noisy.choose_move(&s));
+    assert_eq!(50, reduce_noisy.root_value());
+}
@@ -0,0 +1,155 @@
+// Saved transposition tables should carry the results of a search over to a
+// fresh search, between any of the iterative strategies. Searches shouldn't
+// depend on the table keeping any one entry.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+
+#[test]
+fn resume_analysis() {
+    let mut b = connect4::Board::default();
+    for &i in [3, 2, 4].iter() {
+        let mut moves = Vec::new();
+        connect4::Game::generate_moves(&b, &mut moves);
+        moves[i].apply(&mut b);
+    }
+    let mut search = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    search.set_max_depth(7);
+    let m = search.choose_move(&b);
+    let nodes = search.stats().total_nodes();
+    let mut bytes = Vec::new();
+    search.write_table(&mut bytes).unwrap();
+
+    // A fresh search with the loaded table finds the same result with less work.
+    let mut resumed = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    resumed.read_table(&mut &bytes[..]).unwrap();
+    resumed.set_max_depth(7);
+    assert_eq!(m, resumed.choose_move(&b));
+    assert_eq!(search.root_value(), resumed.root_value());
+    assert!(resumed.stats().total_nodes() * 2 < nodes, "{} {}", resumed.stats(), nodes);
+
+    // Entries are found in a larger table with packed moves, which needs more
+    // bits of their hashes.
+    let mut larger = IterativeSearch::new_packed(
+        connect4::BasicEvaluator,
+        IterativeOptions::new()
+            .with_table_byte_size(1 << 24)
+            .with_replacement_strategy(Replacement::Bucket(4)),
+    );
+    larger.read_table(&mut &bytes[..]).unwrap();
+    larger.set_max_depth(7);
+    assert_eq!(m, larger.choose_move(&b));
+    assert!(larger.stats().total_nodes() * 2 < nodes, "{} {}", larger.stats(), nodes);
+
+    // Tables of other sizes and strategies read the same format.
+    let mut ybw = ParallelYbw::new_packed(
+        connect4::BasicEvaluator,
+        YbwOptions::new().with_table_byte_size(1 << 16),
+    );
+    ybw.read_table(&mut &bytes[..]).unwrap();
+    let mut ybw_bytes = Vec::new();
+    ybw.write_table(&mut ybw_bytes).unwrap();
+    ybw.set_max_depth(7);
+    ybw.choose_move(&b).unwrap();
+    assert_eq!(search.root_value(), ybw.root_value());
+    assert!(ybw.stats().total_nodes() * 2 < nodes, "{} {}", ybw.stats(), nodes);
+    let mut lazy_smp = LazySmp::new_packed(connect4::BasicEvaluator, LazySmpOptions::new());
+    lazy_smp.read_table(&mut &ybw_bytes[..]).unwrap();
+    lazy_smp.set_max_depth(7);
+    lazy_smp.choose_move(&b).unwrap();
+    assert_eq!(search.root_value(), lazy_smp.root_value());
+    assert!(lazy_smp.stats().total_nodes() * 2 < nodes, "{} {}", lazy_smp.stats(), nodes);
+}
+
+#[test]
+fn invalid_tables() {
+    let mut search = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    search.set_max_depth(2);
+    search.choose_move(&connect4::Board::default());
+    let mut bytes = Vec::new();
+    search.write_table(&mut bytes).unwrap();
+
+    assert!(search.read_table(&mut &bytes[1..]).is_err());
+    assert!(search.read_table(&mut &bytes[..bytes.len() - 1]).is_err());
+    // Change the name of the move encoding.
+    let mut renamed = bytes.clone();
+    renamed[10] ^= 1;
+    assert!(search.read_table(&mut &renamed[..]).is_err());
+    // A move that doesn't decode.
+    let mut bad_move = bytes.clone();
+    *bad_move.last_mut().unwrap() = 100;
+    assert!(search.read_table(&mut &bad_move[..]).is_err());
+    assert!(search.read_table(&mut &bytes[..]).is_ok());
+}
+
+#[test]
+fn tiny_tables() {
+    // Searches still find a move and its principal variation when their root
+    // entry may not survive in the table.
+    let b = connect4::Board::default();
+    for strategy in [Replacement::Always, Replacement::DepthPreferred, Replacement::Bucket(2)] {
+        let opts =
+            IterativeOptions::new().with_table_byte_size(1).with_replacement_strategy(strategy);
+        let mut mtdf = MtdfSearch::new(connect4::BasicEvaluator, opts);
+        mtdf.set_max_depth(5);
+        let m = mtdf.choose_move(&b);
+        assert!(m.is_some());
+        assert_eq!(m, mtdf.principal_variation().first().copied());
+
+        let mut iterative = IterativeSearch::new(connect4::BasicEvaluator, opts);
+        iterative.set_max_depth(5);
+        let m = iterative.choose_move(&b);
+        assert!(m.is_some());
+        assert_eq!(m, iterative.principal_variation().first().copied());
+
+        // The helpers replace the root entry while the main thread searches.
+        let mut lazy_smp = LazySmp::new(
+            connect4::BasicEvaluator,
+            LazySmpOptions::new()
+                .with_num_threads(4)
+                .with_table_byte_size(1)
+                .with_replacement_strategy(strategy),
+        );
+        lazy_smp.set_max_depth(5);
+        let m = lazy_smp.choose_move(&b);
+        assert!(m.is_some());
+        assert_eq!(m, lazy_smp.principal_variation().first().copied());
+
+        let mut ybw = ParallelYbw::new(
+            connect4::BasicEvaluator,
+            YbwOptions::new().with_table_byte_size(1).with_replacement_strategy(strategy),
+        );
+        ybw.set_max_depth(5);
+        let m = ybw.choose_move(&b);
+        assert!(m.is_some());
+        assert_eq!(m, ybw.principal_variation().first().copied());
+        assert_eq!(ybw.root_value(), iterative.root_value());
+    }
+}
+
+// Scales values beyond what a packed table keeps exactly.
+#[derive(Clone)]
+struct LargeEvaluator;
+
+impl Evaluator for LargeEvaluator {
+    type G = connect4::Game;
+    fn evaluate(&self, b: &connect4::Board) -> Evaluation {
+        connect4::BasicEvaluator.evaluate(b) * 1000 + 100_000
+    }
+}
+
+#[test]
+fn packed_root_value() {
+    // The root value comes from the search, not from its packed entry.
+    let b = connect4::Board::default();
+    let mut iterative = IterativeSearch::new(LargeEvaluator, IterativeOptions::new());
+    iterative.set_max_depth(4);
+    iterative.choose_move(&b);
+    let mut ybw = ParallelYbw::new_packed(LargeEvaluator, YbwOptions::new());
+    ybw.set_max_depth(4);
+    ybw.choose_move(&b);
+    assert_eq!(ybw.root_value(), iterative.root_value());
+}
@@ -0,0 +1,70 @@
+// Searches under a game clock should budget their time so that they never run
+// out, whichever strategy is used.
+
+extern crate minimax;
+#[path = "../examples/connect4.rs"]
+mod connect4;
+
+use minimax::*;
+use std::time::{Duration, Instant};
+
+// Play a game against itself on a shared clock, and return the time left.
+fn play_on_clock<S: Strategy<connect4::Game>>(
+    search: &mut S, set_clock: fn(&mut S, TimeControl), clock: Duration,
+) -> Duration {
+    let mut remaining = clock;
+    let mut b = connect4::Board::default();
+    while connect4::Game::get_winner(&b).is_none() {
+        set_clock(search, TimeControl::new(remaining));
+        let start = Instant::now();
+        let m = search.choose_move(&b).unwrap();
+        remaining = remaining.checked_sub(start.elapsed()).expect("ran out of time");
+        m.apply(&mut b);
+    }
+    remaining
+}
+
+#[test]
+fn never_flag() {
+    let clock = Duration::from_secs(2);
+    let mut iterative = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    play_on_clock(&mut iterative, IterativeSearch::set_time_control, clock);
+    let mut lazy_smp = LazySmp::new(connect4::BasicEvaluator, LazySmpOptions::new());
+    play_on_clock(&mut lazy_smp, LazySmp::set_time_control, clock);
+    let mut ybw = ParallelYbw::new(connect4::BasicEvaluator, YbwOptions::new());
+    play_on_clock(&mut ybw, ParallelYbw::set_time_control, clock);
+}
+
+#[test]
+fn moves_to_go() {
+    let mut search = IterativeSearch::new(connect4::BasicEvaluator, IterativeOptions::new());
+    let b = connect4::Board::default();
+
+    // Sudden death spreads the time over many moves. The bounds below leave
+    // a wide margin over the budgets, so they only fail if most of the clock
+    // is spent.
+    let clock = Duration::from_secs(3);
+    search.set_time_control(TimeControl::new(clock));
+    let start = Instant::now();
+    search.choose_move(&b).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+    let sudden_death_depth = search.stats().depth;
+
+    // The last move before the time control can use more of the clock.
+    search.set_time_control(TimeControl::new(clock).with_moves_to_go(1));
+    search.choose_move(&b).unwrap();
+    assert!(search.stats().depth > sudden_death_depth, "{}", search.stats());
+
+    // The increment can't be spent before it's added to the clock.
+    search.set_time_control(
+        TimeControl::new(Duration::from_millis(30)).with_increment(Duration::from_secs(10)),
+    );
+    let start = Instant::now();
+    search.choose_move(&b).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+
+    // A fixed depth replaces the time control.
+    search.set_max_depth(2);
+    search.choose_move(&b).unwrap();
+    assert_eq!(2, search.stats().depth);
+}
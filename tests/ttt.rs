@@ -9,8 +9,8 @@ use minimax::Negamax;
 // Ensure that two players using negamax always results in a draw.
 #[test]
 fn test_ttt_negamax_always_draws() {
-    let mut s1 = Negamax::new(ttt::Evaluator::default(), 10);
-    let mut s2 = Negamax::new(ttt::Evaluator::default(), 10);
+    let mut s1 = Negamax::new(ttt::Evaluator, 10);
+    let mut s2 = Negamax::new(ttt::Evaluator, 10);
     for _ in 0..100 {
         assert_eq!(battle_royale(&mut s1, &mut s2), None);
     }
@@ -21,9 +21,29 @@ fn test_ttt_negamax_always_draws() {
 #[test]
 fn test_ttt_negamax_vs_random_always_wins_or_draws() {
     use minimax::strategies::random::Random;
-    let mut s1 = Negamax::new(ttt::Evaluator::default(), 10);
+    let mut s1 = Negamax::new(ttt::Evaluator, 10);
     let mut s2 = Random::new();
     for _ in 0..100 {
         assert_ne!(battle_royale(&mut s1, &mut s2), Some(1));
     }
 }
+
+// Ensure that a player using Monte Carlo Tree Search against a random one
+// always results in either a draw or a win for the former player.
+#[test]
+fn test_ttt_mcts_vs_random_always_wins_or_draws() {
+    use minimax::strategies::random::Random;
+    use minimax::{MctsOptions, MonteCarloTreeSearch};
+    for opts in [
+        MctsOptions::default(),
+        MctsOptions::default().with_num_threads(4),
+        MctsOptions::default().with_num_threads(4).with_root_parallelism(),
+    ] {
+        let mut s1 = MonteCarloTreeSearch::<ttt::Game>::new(opts);
+        s1.set_max_rollouts(10000);
+        let mut s2 = Random::new();
+        for _ in 0..10 {
+            assert_ne!(battle_royale(&mut s1, &mut s2), Some(1));
+        }
+    }
+}